@@ -0,0 +1,89 @@
+//! Compares the bulk paths for byte and number vectors against encoding them item by item.
+
+use buffin::{Error, FromBytes, ToBytes};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use nom::IResult;
+use std::hint::black_box;
+
+/// A byte that only implements the required methods, so it takes the item by item path.
+#[derive(Clone, Copy)]
+struct Byte(u8);
+
+impl ToBytes for Byte {
+    const MAX_SIZE: Option<usize> = Some(1);
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> buffin::Result<usize> {
+        self.0.to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        1
+    }
+}
+
+impl FromBytes<'_> for Byte {
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+        let (buffer, byte) = u8::from_bytes(buffer)?;
+        Ok((buffer, Byte(byte)))
+    }
+}
+
+const SIZES: [usize; 2] = [4 * 1024, 64 * 1024];
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+
+    for size in SIZES {
+        let bytes: Vec<u8> = (0..size).map(|i| i as u8).collect();
+        let items: Vec<Byte> = bytes.iter().copied().map(Byte).collect();
+        let numbers: Vec<u32> = (0..size as u32 / 4).collect();
+        let mut buffer = vec![0; size + 16];
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("Vec<u8>", size), &bytes, |b, bytes| {
+            b.iter(|| black_box(bytes).to_bytes(&mut buffer).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("Vec<Byte>", size), &items, |b, items| {
+            b.iter(|| black_box(items).to_bytes(&mut buffer).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("Vec<u32>", size),
+            &numbers,
+            |b, numbers| b.iter(|| black_box(numbers).to_bytes(&mut buffer).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+/// Encodes the value into a new vector.
+fn encoded<T: ToBytes>(value: &T) -> Vec<u8> {
+    value.to_vec().unwrap()
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+
+    for size in SIZES {
+        let bytes = encoded(&(0..size).map(|i| i as u8).collect::<Vec<u8>>());
+        let numbers = encoded(&(0..size as u32 / 4).collect::<Vec<u32>>());
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("Vec<u8>", size), &bytes, |b, bytes| {
+            b.iter(|| Vec::<u8>::from_bytes(black_box(bytes)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("Vec<Byte>", size), &bytes, |b, bytes| {
+            b.iter(|| Vec::<Byte>::from_bytes(black_box(bytes)).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("Vec<u32>", size),
+            &numbers,
+            |b, numbers| b.iter(|| Vec::<u32>::from_bytes(black_box(numbers)).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+//! Pops many small messages from a buffer, like a receive loop does.
+
+use buffin::Buffin;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+type Message = (u8, u16, u32);
+
+const COUNTS: [usize; 2] = [64, 4096];
+
+fn pop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pop");
+
+    for count in COUNTS {
+        let mut raw = vec![0; count * 7 + 16];
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_function(BenchmarkId::new("messages", count), |b| {
+            b.iter(|| {
+                let mut buffer = Buffin::new(&mut raw);
+                for i in 0..count {
+                    buffer.add(&(i as u8, i as u16, i as u32)).unwrap();
+                }
+                while let Ok(message) = buffer.pop::<Message>() {
+                    black_box(message);
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, pop);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+//! Used by the code `buffin_derive` generates, so that crates using it don't have to depend on
+//! anything but buffin. Not part of the public API.
+
+use crate::{Error, ErrorKind, Result, ToBytes};
+use nom::IResult;
+
+pub use nom;
+
+/// Encodes a value with the functions given in `#[buffin(with = "...")]` or
+/// `#[buffin(serialize_with = "...", encoded_len_with = "...")]`.
+pub struct With<'a, T> {
+    value: &'a T,
+    to_bytes: fn(&T, &mut [u8]) -> Result<usize>,
+    encoded_len: fn(&T) -> usize,
+}
+
+impl<'a, T> With<'a, T> {
+    pub fn new(
+        value: &'a T,
+        to_bytes: fn(&T, &mut [u8]) -> Result<usize>,
+        encoded_len: fn(&T) -> usize,
+    ) -> Self {
+        Self {
+            value,
+            to_bytes,
+            encoded_len,
+        }
+    }
+}
+
+impl<T> ToBytes for With<'_, T> {
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        (self.to_bytes)(self.value, buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        (self.encoded_len)(self.value)
+    }
+}
+
+/// Adjusts the result of decoding from the payload of a `#[buffin(length_delimited)]` enum
+/// variant, which is followed by `rest` more bytes in the buffer.
+///
+/// The payload is complete, so running out of it is an error rather than incomplete.
+pub fn in_payload<T>(result: IResult<&[u8], T, Error>, rest: usize) -> IResult<&[u8], T, Error> {
+    result.map_err(|err| match err {
+        nom::Err::Incomplete(_) => nom::Err::Failure(
+            Error::at_input(ErrorKind::Parse(nom::error::ErrorKind::Eof), &[]).followed_by(rest),
+        ),
+        err => err.map(|err| err.followed_by(rest)),
+    })
+}
@@ -1,120 +1,331 @@
-use crate::{Buffin, FromBytes, ToBytes};
-use eyre::Result;
+use crate::{Buffin, Error, ErrorKind, FromBytes, Limits, Result, ToBytes, len_prefix, max_size};
 use nom::{
     IResult, Parser,
     branch::alt,
     bytes::tag,
     combinator::map,
-    number::streaming::{le_u8, le_u16, le_u32, le_u64},
+    number::streaming::{
+        le_f32, le_f64, le_i8, le_i16, le_i32, le_i64, le_i128, le_u8, le_u16, le_u32, le_u64,
+        le_u128,
+    },
 };
 
-#[cfg(not(feature = "no_std"))]
-use eyre::bail;
+use nom::bytes::streaming::take;
 
-#[cfg(not(feature = "no_std"))]
-use nom::{
-    bytes::streaming::take,
-    error::{Error, ErrorKind},
+use core::{
+    num::{
+        NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroU8, NonZeroU16,
+        NonZeroU32, NonZeroU64, NonZeroU128,
+    },
+    ops::RangeInclusive,
 };
 
-#[cfg(not(feature = "no_std"))]
-use std::{ops::RangeInclusive, path::PathBuf};
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, boxed::Box, string::String, vec::Vec};
 
-#[cfg(feature = "no_std")]
-use core::ops::RangeInclusive;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
 
-#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "alloc")]
 impl ToBytes for String {
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
-        let mut buffer = Buffin::new(buffer);
-
-        buffer.add(&(self.bytes().len() as u32))?;
-        buffer.add_bytes(self.as_bytes())?;
+        len_prefix::to_bytes::<u32, str>(self, buffer)
+    }
 
-        Ok(buffer.len())
+    fn encoded_len(&self) -> usize {
+        len_prefix::encoded_len::<u32, str>(self)
     }
 }
 
-#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "alloc")]
 impl ToBytes for &String {
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
-        let mut buffer = Buffin::new(buffer);
+        len_prefix::to_bytes::<u32, str>(self, buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        len_prefix::encoded_len::<u32, str>(self)
+    }
+}
 
-        buffer.add(&(self.bytes().len() as u32))?;
-        buffer.add_bytes(self.as_bytes())?;
+#[cfg(feature = "alloc")]
+impl FromBytes<'_> for String {
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
 
-        Ok(buffer.len())
+    fn from_bytes_limited(buffer: &[u8], limits: Limits) -> IResult<&[u8], Self, Error> {
+        len_prefix::from_bytes::<u32, _>(buffer, limits)
     }
 }
 
-#[cfg(not(feature = "no_std"))]
-impl FromBytes for String {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        let (buffer, len) = le_u32(buffer)?;
-        let (buffer, bytes) = take(len)(buffer)?;
-        match String::from_utf8(bytes.to_vec()) {
-            Ok(s) => Ok((buffer, s)),
-            Err(_) => IResult::Err(nom::Err::Failure(Error {
-                input: buffer,
-                code: ErrorKind::Fail,
-            })),
-        }
+impl ToBytes for &str {
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        len_prefix::to_bytes::<u32, str>(self, buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        len_prefix::encoded_len::<u32, str>(self)
     }
 }
 
-impl ToBytes for u32 {
+impl<'de: 'a, 'a> FromBytes<'de> for &'a str {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        len_prefix::from_bytes::<u32, _>(buffer, limits)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ToBytes for Cow<'_, str> {
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
-        let mut buffer = Buffin::new(buffer);
-        buffer.add_bytes(&self.to_le_bytes())?;
-        Ok(buffer.len())
+        self.as_ref().to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.as_ref().encoded_len()
     }
 }
 
-impl FromBytes for u32 {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        le_u32(buffer)
+#[cfg(feature = "alloc")]
+impl<'de: 'a, 'a> FromBytes<'de> for Cow<'a, str> {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
     }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        len_prefix::from_bytes::<u32, _>(buffer, limits)
+    }
+}
+
+// ToBytes and FromBytes for numbers, all little endian.
+macro_rules! impl_number {
+    ($($ty:ty => $parser:ident),* $(,)?) => {
+        $(
+            impl ToBytes for $ty {
+                const MAX_SIZE: Option<usize> = Some(size_of::<Self>());
+
+                fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+                    let mut buffer = Buffin::new(buffer);
+                    buffer.add_bytes(&self.to_le_bytes())?;
+                    Ok(buffer.len())
+                }
+
+                fn encoded_len(&self) -> usize {
+                    size_of::<Self>()
+                }
+
+                fn slice_to_bytes(items: &[Self], buffer: &mut [u8]) -> Result<usize> {
+                    let mut buffer = Buffin::new(buffer);
+                    let bytes = buffer.claim(items.len() * size_of::<Self>())?;
+
+                    for (chunk, item) in bytes.chunks_exact_mut(size_of::<Self>()).zip(items) {
+                        chunk.copy_from_slice(&item.to_le_bytes());
+                    }
+
+                    Ok(buffer.len())
+                }
+            }
+
+            impl FromBytes<'_> for $ty {
+                fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+                    $parser(buffer)
+                }
+
+                #[cfg(feature = "alloc")]
+                fn vec_from_bytes(
+                    buffer: &[u8],
+                    len: usize,
+                    _limits: Limits,
+                ) -> IResult<&[u8], Vec<Self>, Error> {
+                    let (buffer, bytes) = take_items(buffer, len, size_of::<Self>())?;
+                    let result = bytes
+                        .chunks_exact(size_of::<Self>())
+                        .map(|chunk| Self::from_le_bytes(chunk.try_into().expect("chunks are exact")))
+                        .collect();
+                    Ok((buffer, result))
+                }
+            }
+        )*
+    };
 }
 
-impl ToBytes for u64 {
+/// Takes the bytes of `len` items of `size` bytes each.
+#[cfg(feature = "alloc")]
+fn take_items(buffer: &[u8], len: usize, size: usize) -> IResult<&[u8], &[u8], Error> {
+    match len.checked_mul(size) {
+        Some(total) => take(total)(buffer),
+        None => Err(nom::Err::Failure(Error::at_input(
+            ErrorKind::LengthLimitExceeded {
+                len,
+                limit: usize::MAX / size,
+            },
+            buffer,
+        ))),
+    }
+}
+
+impl_number! {
+    u16 => le_u16,
+    u32 => le_u32,
+    u64 => le_u64,
+    u128 => le_u128,
+    i8 => le_i8,
+    i16 => le_i16,
+    i32 => le_i32,
+    i64 => le_i64,
+    i128 => le_i128,
+    f32 => le_f32,
+    f64 => le_f64,
+}
+
+// u8 is done by hand, so that slices and arrays of bytes can be copied in one go.
+impl ToBytes for u8 {
+    const MAX_SIZE: Option<usize> = Some(1);
+
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
         let mut buffer = Buffin::new(buffer);
-        buffer.add_bytes(&self.to_le_bytes())?;
+        buffer.add_bytes(&[*self])?;
+        Ok(buffer.len())
+    }
+
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
+    fn slice_to_bytes(items: &[Self], buffer: &mut [u8]) -> Result<usize> {
+        let mut buffer = Buffin::new(buffer);
+        buffer.add_bytes(items)?;
         Ok(buffer.len())
     }
 }
 
-impl FromBytes for u64 {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        le_u64(buffer)
+impl FromBytes<'_> for u8 {
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+        le_u8(buffer)
+    }
+
+    fn array_from_bytes<const N: usize>(
+        buffer: &[u8],
+        _limits: Limits,
+    ) -> IResult<&[u8], [Self; N], Error> {
+        let (buffer, bytes) = take(N)(buffer)?;
+        let mut array = [0; N];
+        array.copy_from_slice(bytes);
+        Ok((buffer, array))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn vec_from_bytes(
+        buffer: &[u8],
+        len: usize,
+        _limits: Limits,
+    ) -> IResult<&[u8], Vec<Self>, Error> {
+        let (buffer, bytes) = take(len)(buffer)?;
+        Ok((buffer, bytes.to_vec()))
     }
 }
 
-impl ToBytes for u16 {
+// NonZero numbers are encoded like the number they wrap, and zero is rejected when decoding.
+macro_rules! impl_non_zero {
+    ($($ty:ty => $inner:ty),* $(,)?) => {
+        $(
+            impl ToBytes for $ty {
+                const MAX_SIZE: Option<usize> = <$inner>::MAX_SIZE;
+
+                fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+                    self.get().to_bytes(buffer)
+                }
+
+                fn encoded_len(&self) -> usize {
+                    size_of::<Self>()
+                }
+            }
+
+            impl FromBytes<'_> for $ty {
+                fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+                    let (remainder, value) = <$inner>::from_bytes(buffer)?;
+                    match <$ty>::new(value) {
+                        Some(value) => Ok((remainder, value)),
+                        None => Err(nom::Err::Failure(Error::at_input(
+                            ErrorKind::InvalidValue { value: 0 },
+                            buffer,
+                        ))),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_non_zero! {
+    NonZeroU8 => u8,
+    NonZeroU16 => u16,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64,
+    NonZeroU128 => u128,
+    NonZeroI8 => i8,
+    NonZeroI16 => i16,
+    NonZeroI32 => i32,
+    NonZeroI64 => i64,
+    NonZeroI128 => i128,
+}
+
+impl ToBytes for bool {
+    const MAX_SIZE: Option<usize> = Some(1);
+
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
-        let mut buffer = Buffin::new(buffer);
-        buffer.add_bytes(&self.to_le_bytes())?;
-        Ok(buffer.len())
+        (*self as u8).to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        1
     }
 }
 
-impl FromBytes for u16 {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        le_u16(buffer)
+impl FromBytes<'_> for bool {
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+        match le_u8(buffer)? {
+            (remainder, 0) => Ok((remainder, false)),
+            (remainder, 1) => Ok((remainder, true)),
+            (_, value) => Err(nom::Err::Failure(Error::at_input(
+                ErrorKind::InvalidValue {
+                    value: value as u64,
+                },
+                buffer,
+            ))),
+        }
     }
 }
 
-impl ToBytes for u8 {
+// Chars are encoded as their u32 value.
+impl ToBytes for char {
+    const MAX_SIZE: Option<usize> = Some(4);
+
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
-        let mut buffer = Buffin::new(buffer);
-        buffer.add_bytes(&self.to_le_bytes())?;
-        Ok(buffer.len())
+        (*self as u32).to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        4
     }
 }
 
-impl FromBytes for u8 {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        le_u8(buffer)
+impl FromBytes<'_> for char {
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+        let (remainder, value) = le_u32(buffer)?;
+        match char::from_u32(value) {
+            Some(c) => Ok((remainder, c)),
+            None => Err(nom::Err::Failure(Error::at_input(
+                ErrorKind::InvalidValue {
+                    value: value as u64,
+                },
+                buffer,
+            ))),
+        }
     }
 }
 
@@ -124,18 +335,25 @@ where
     T: ToBytes,
 {
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
-        let mut buffer = Buffin::new(buffer);
+        len_prefix::to_bytes::<u32, [T]>(self, buffer)
+    }
 
-        buffer.add(&(self.len() as u32))?;
-        for item in self.iter() {
-            buffer.add(item)?;
-        }
+    fn encoded_len(&self) -> usize {
+        len_prefix::encoded_len::<u32, [T]>(self)
+    }
+}
 
-        Ok(buffer.len())
+impl<'de: 'a, 'a> FromBytes<'de> for &'a [u8] {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        len_prefix::from_bytes::<u32, _>(buffer, limits)
     }
 }
 
-#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "alloc")]
 impl<T> ToBytes for Vec<T>
 where
     T: ToBytes,
@@ -143,9 +361,13 @@ where
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
         self.as_slice().to_bytes(buffer)
     }
+
+    fn encoded_len(&self) -> usize {
+        self.as_slice().encoded_len()
+    }
 }
 
-#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "alloc")]
 impl<T> ToBytes for &Vec<T>
 where
     T: ToBytes,
@@ -153,30 +375,105 @@ where
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
         self.as_slice().to_bytes(buffer)
     }
+
+    fn encoded_len(&self) -> usize {
+        self.as_slice().encoded_len()
+    }
 }
 
-#[cfg(not(feature = "no_std"))]
-impl<T> FromBytes for Vec<T>
+#[cfg(feature = "alloc")]
+impl<'de, T> FromBytes<'de> for Vec<T>
 where
-    T: FromBytes,
+    T: FromBytes<'de>,
 {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        let (buffer, len) = le_u32(buffer)?;
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
 
-        let mut buffer = buffer;
-        let mut result = vec![];
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        len_prefix::from_bytes::<u32, _>(buffer, limits)
+    }
+}
 
-        for _ in 0..len {
-            let (b, it) = T::from_bytes(&buffer)?;
-            result.push(it);
-            buffer = b;
-        }
+// Arrays have a known length, so unlike slices they are encoded without a length prefix.
+impl<T: ToBytes, const N: usize> ToBytes for [T; N] {
+    const MAX_SIZE: Option<usize> = max_size::times(T::MAX_SIZE, N);
 
-        Ok((buffer, result))
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        T::slice_to_bytes(self, buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.iter().map(T::encoded_len).sum()
     }
 }
 
+impl<'de, T: FromBytes<'de>, const N: usize> FromBytes<'de> for [T; N] {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        T::array_from_bytes(buffer, limits.nested(buffer)?)
+    }
+}
+
+// Tuples are encoded as their items, one after the other.
+macro_rules! impl_tuple {
+    ($($ty:ident $item:ident),+) => {
+        impl<$($ty: ToBytes),+> ToBytes for ($($ty,)+) {
+            const MAX_SIZE: Option<usize> = {
+                let size = Some(0);
+                $( let size = max_size::sum(size, $ty::MAX_SIZE); )+
+                size
+            };
+
+            fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+                let mut buffer = Buffin::new(buffer);
+                let ($($item,)+) = self;
+                $( buffer.add($item)?; )+
+                Ok(buffer.len())
+            }
+
+            fn encoded_len(&self) -> usize {
+                let ($($item,)+) = self;
+                0 $( + $item.encoded_len() )+
+            }
+        }
+
+        impl<'de, $($ty: FromBytes<'de>),+> FromBytes<'de> for ($($ty,)+) {
+            fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+                Self::from_bytes_limited(buffer, Limits::global())
+            }
+
+            fn from_bytes_limited(
+                buffer: &'de [u8],
+                limits: Limits,
+            ) -> IResult<&'de [u8], Self, Error> {
+                let limits = limits.nested(buffer)?;
+                $( let (buffer, $item) = $ty::from_bytes_limited(buffer, limits)?; )+
+                Ok((buffer, ($($item,)+)))
+            }
+        }
+    };
+}
+
+impl_tuple!(A a);
+impl_tuple!(A a, B b);
+impl_tuple!(A a, B b, C c);
+impl_tuple!(A a, B b, C c, D d);
+impl_tuple!(A a, B b, C c, D d, E e);
+impl_tuple!(A a, B b, C c, D d, E e, F f);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g, H h);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j, K k);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j, K k, L l);
+
 impl<T: ToBytes> ToBytes for RangeInclusive<T> {
+    const MAX_SIZE: Option<usize> = max_size::sum(T::MAX_SIZE, T::MAX_SIZE);
+
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
         let mut buffer = Buffin::new(buffer);
 
@@ -185,17 +482,28 @@ impl<T: ToBytes> ToBytes for RangeInclusive<T> {
 
         Ok(buffer.len())
     }
+
+    fn encoded_len(&self) -> usize {
+        self.start().encoded_len() + self.end().encoded_len()
+    }
 }
 
-impl<T: FromBytes> FromBytes for RangeInclusive<T> {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        let (buffer, start) = T::from_bytes(buffer)?;
-        let (buffer, end) = T::from_bytes(buffer)?;
+impl<'de, T: FromBytes<'de>> FromBytes<'de> for RangeInclusive<T> {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        let limits = limits.nested(buffer)?;
+        let (buffer, start) = T::from_bytes_limited(buffer, limits)?;
+        let (buffer, end) = T::from_bytes_limited(buffer, limits)?;
         Ok((buffer, RangeInclusive::new(start, end)))
     }
 }
 
 impl<T: ToBytes> ToBytes for Option<T> {
+    const MAX_SIZE: Option<usize> = max_size::sum(Some(1), T::MAX_SIZE);
+
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
         let mut buffer = Buffin::new(buffer);
 
@@ -211,37 +519,83 @@ impl<T: ToBytes> ToBytes for Option<T> {
 
         Ok(buffer.len())
     }
+
+    fn encoded_len(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::encoded_len)
+    }
 }
 
-impl<T: FromBytes> FromBytes for Option<T> {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
+impl<'de, T: FromBytes<'de>> FromBytes<'de> for Option<T> {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        let limits = limits.nested(buffer)?;
         alt((
-            map((tag("+"), T::from_bytes), |(_, item)| Some(item)),
+            map(
+                (tag("+"), |buffer| T::from_bytes_limited(buffer, limits)),
+                |(_, item)| Some(item),
+            ),
             map(tag("-"), |_| None),
         ))
         .parse(buffer)
     }
 }
 
-#[cfg(not(feature = "no_std"))]
+#[cfg(feature = "alloc")]
+impl<T: ToBytes> ToBytes for Box<T> {
+    // Boxes are how recursive types are built, and asking `T` would make `MAX_SIZE` depend on
+    // itself, so they're left unbounded.
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_ref().to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.as_ref().encoded_len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, T: FromBytes<'de>> FromBytes<'de> for Box<T> {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        let (buffer, item) = T::from_bytes_limited(buffer, limits)?;
+        Ok((buffer, Box::new(item)))
+    }
+}
+
+#[cfg(feature = "std")]
 impl ToBytes for PathBuf {
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
         let mut buffer = Buffin::new(buffer);
 
         let path = match self.to_str() {
-            Some(p) => p.to_string(),
-            None => bail!("invalid path"),
+            Some(p) => p,
+            None => return Err(Error::new(ErrorKind::InvalidUtf8)),
         };
 
         buffer.add(&path)?;
         Ok(buffer.len())
     }
+
+    fn encoded_len(&self) -> usize {
+        4 + self.as_os_str().len()
+    }
 }
 
-#[cfg(not(feature = "no_std"))]
-impl FromBytes for PathBuf {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self> {
-        let (buffer, path) = String::from_bytes(buffer)?;
+#[cfg(feature = "std")]
+impl FromBytes<'_> for PathBuf {
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &[u8], limits: Limits) -> IResult<&[u8], Self, Error> {
+        let (buffer, path) = String::from_bytes_limited(buffer, limits)?;
         Ok((buffer, PathBuf::from(path)))
     }
 }
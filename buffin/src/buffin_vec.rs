@@ -0,0 +1,134 @@
+use crate::{FromBytes, FromBytesOwned, Limits, PopFailure, Result, ToBytes, peek_bytes};
+use alloc::vec::Vec;
+
+/// Like [`Buffin`](crate::Buffin), but owns its bytes and grows as needed.
+///
+/// Values are sized with [`ToBytes::encoded_len`] before they're encoded, so adding only fails if
+/// encoding does.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuffinVec {
+    // The used bytes are `data[head..]`.
+    data: Vec<u8>,
+    head: usize,
+}
+
+impl BuffinVec {
+    /// Create a new, empty instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new, empty instance with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(capacity),
+            head: 0,
+        }
+    }
+
+    /// Returns the number of used bytes.
+    pub fn len(&self) -> usize {
+        self.data.len() - self.head
+    }
+
+    /// Returns the used bytes as a slice.
+    pub fn bytes(&self) -> &[u8] {
+        &self.data[self.head..]
+    }
+
+    /// Empty the buffer.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.head = 0;
+    }
+
+    /// Returns true if the buffer is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the used bytes as a vector.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.data.drain(..self.head);
+        self.data
+    }
+
+    /// Adds the given bytes as is.
+    pub fn add_bytes(&mut self, bytes: &[u8]) {
+        self.make_room(bytes.len());
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Adds something that implements ToBytes.
+    pub fn add<T: ToBytes>(&mut self, b: &T) -> Result<()> {
+        let n = b.encoded_len();
+        self.make_room(n);
+
+        let start = self.data.len();
+        self.data.resize(start + n, 0);
+
+        match b.to_bytes(&mut self.data[start..]) {
+            Ok(written) => {
+                self.data.truncate(start + written);
+                Ok(())
+            }
+            Err(err) => {
+                self.data.truncate(start);
+                Err(err.offset_by(self.len()))
+            }
+        }
+    }
+
+    /// Drops the bytes that have been popped, if that saves growing the vector to fit `n` more.
+    fn make_room(&mut self, n: usize) {
+        if self.head > 0 && self.data.capacity() - self.data.len() < n {
+            self.data.drain(..self.head);
+            self.head = 0;
+        }
+    }
+
+    /// Remove the n first bytes.
+    pub fn remove_first(&mut self, n: usize) {
+        assert!(n <= self.len(), "cannot remove more bytes than are used");
+
+        self.head += n;
+        if self.head == self.data.len() {
+            self.clear();
+        }
+    }
+
+    /// Attempts to pop the first item of the given type, within the global [`Limits`].
+    pub fn pop<T: FromBytesOwned>(&mut self) -> Result<T, PopFailure> {
+        self.pop_with(Limits::global())
+    }
+
+    /// Attempts to pop the first item of the given type, within the given limits.
+    pub fn pop_with<T: FromBytesOwned>(&mut self, limits: Limits) -> Result<T, PopFailure> {
+        let (result, removed) = self.peek_with::<T>(limits)?;
+        self.remove_first(removed);
+        Ok(result)
+    }
+
+    /// Attempts to decode the first item of the given type without removing it, within the global
+    /// [`Limits`].
+    ///
+    /// See [`Buffin::peek`](crate::Buffin::peek).
+    pub fn peek<'b, T: FromBytes<'b>>(&'b self) -> Result<(T, usize), PopFailure> {
+        self.peek_with(Limits::global())
+    }
+
+    /// Like [`BuffinVec::peek`], but within the given limits.
+    pub fn peek_with<'b, T: FromBytes<'b>>(
+        &'b self,
+        limits: Limits,
+    ) -> Result<(T, usize), PopFailure> {
+        peek_bytes(self.bytes(), limits)
+    }
+}
+
+impl From<Vec<u8>> for BuffinVec {
+    fn from(data: Vec<u8>) -> Self {
+        Self { data, head: 0 }
+    }
+}
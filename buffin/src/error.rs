@@ -0,0 +1,273 @@
+use core::fmt;
+use nom::error::ParseError;
+
+/// The error type used when encoding and decoding.
+///
+/// Besides what went wrong, it keeps track of where it went wrong: the byte offset into the
+/// buffer, and the path of fields leading up to the value that failed, as recorded by the derive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    // Until the error has been located, this is the number of bytes remaining in the input.
+    offset: usize,
+    located: bool,
+    path: FieldPath,
+}
+
+/// What went wrong while encoding or decoding.
+///
+/// More kinds may be added, so matching on it needs a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The buffer did not have room for the bytes being added.
+    BufferFull { requested: usize, available: usize },
+    /// The bytes in the buffer did not match any of the expected tags.
+    BadTag { seen: SeenBytes },
+    /// A string was not valid UTF-8.
+    InvalidUtf8,
+    /// A value was decoded, but it isn't valid for the type, e.g. a bool that isn't 0 or 1.
+    InvalidValue { value: u64 },
+    /// A length was larger than allowed.
+    LengthLimitExceeded { len: usize, limit: usize },
+    /// Values were nested more deeply than allowed.
+    DepthLimitExceeded { limit: usize },
+    /// One of the nom parsers failed.
+    Parse(nom::error::ErrorKind),
+    /// Anything else, typically from a hand written implementation.
+    Custom(&'static str),
+}
+
+impl Error {
+    /// Create a new error of the given kind.
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            offset: 0,
+            located: true,
+            path: FieldPath::default(),
+        }
+    }
+
+    /// Create a new error with a custom message.
+    pub fn custom(message: &'static str) -> Self {
+        Self::new(ErrorKind::Custom(message))
+    }
+
+    /// Create a new decoding error, where `input` is the part of the buffer that caused it.
+    ///
+    /// The offset is resolved once the error reaches [`Buffin::pop`](crate::Buffin::pop), or
+    /// by calling [`Error::located_in`].
+    pub fn at_input(kind: ErrorKind, input: &[u8]) -> Self {
+        Self {
+            offset: input.len(),
+            located: false,
+            ..Self::new(kind)
+        }
+    }
+
+    /// Returns what went wrong.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the byte offset into the buffer where the error occurred.
+    ///
+    /// A decoding error is only located once it reaches [`Buffin::pop`](crate::Buffin::pop) or
+    /// [`Error::located_in`]. Until then, e.g. when calling [`FromBytes::from_bytes`] directly, the
+    /// offset isn't known, and this returns `None`.
+    ///
+    /// [`FromBytes::from_bytes`]: crate::FromBytes::from_bytes
+    pub fn offset(&self) -> Option<usize> {
+        self.located.then_some(self.offset)
+    }
+
+    /// Returns the path of fields leading up to the value that failed.
+    pub fn path(&self) -> &FieldPath {
+        &self.path
+    }
+
+    /// Records that the error occurred inside the given field, e.g. `"Message::Say.channel"`.
+    ///
+    /// This is called by the derived implementations.
+    pub fn in_field(mut self, field: &'static str) -> Self {
+        self.path.push(field);
+        self
+    }
+
+    /// Moves the offset of an encoding error forward by `n` bytes.
+    pub fn offset_by(mut self, n: usize) -> Self {
+        self.offset += n;
+        self
+    }
+
+    /// Accounts for `n` more bytes following the input a decoding error was found in, e.g. when
+    /// decoding from a slice in the middle of the buffer.
+    pub(crate) fn followed_by(mut self, n: usize) -> Self {
+        if !self.located {
+            self.offset += n;
+        }
+        self
+    }
+
+    /// Resolves the offset of a decoding error, given the whole buffer that was being decoded.
+    pub fn located_in(mut self, input: &[u8]) -> Self {
+        if !self.located {
+            self.offset = input.len().saturating_sub(self.offset);
+            self.located = true;
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(offset) = self.offset() {
+            write!(f, " at offset {offset}")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::BufferFull {
+                requested,
+                available,
+            } => write!(
+                f,
+                "buffer is too small, {requested} bytes requested but only {available} available"
+            ),
+            ErrorKind::BadTag { seen } => write!(f, "unexpected tag, saw {seen:?}"),
+            ErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ErrorKind::InvalidValue { value } => write!(f, "invalid value {value}"),
+            ErrorKind::LengthLimitExceeded { len, limit } => {
+                write!(f, "length {len} exceeds the limit of {limit}")
+            }
+            ErrorKind::DepthLimitExceeded { limit } => {
+                write!(f, "nesting exceeds the depth limit of {limit}")
+            }
+            ErrorKind::Parse(kind) => write!(f, "parse error ({})", kind.description()),
+            ErrorKind::Custom(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl ParseError<&[u8]> for Error {
+    fn from_error_kind(input: &[u8], kind: nom::error::ErrorKind) -> Self {
+        let kind = match kind {
+            nom::error::ErrorKind::Tag => ErrorKind::BadTag {
+                seen: SeenBytes::new(input),
+            },
+            other => ErrorKind::Parse(other),
+        };
+        Self::at_input(kind, input)
+    }
+
+    fn append(_input: &[u8], _kind: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+/// The first few bytes found where a tag was expected.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SeenBytes {
+    bytes: [u8; Self::CAPACITY],
+    len: u8,
+}
+
+impl SeenBytes {
+    /// The maximum number of bytes that are kept.
+    pub const CAPACITY: usize = 8;
+
+    fn new(input: &[u8]) -> Self {
+        let len = input.len().min(Self::CAPACITY);
+        let mut bytes = [0; Self::CAPACITY];
+        bytes[..len].copy_from_slice(&input[..len]);
+        Self {
+            bytes,
+            len: len as u8,
+        }
+    }
+
+    /// Returns the bytes that were seen.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl fmt::Debug for SeenBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x?}", self.as_bytes())
+    }
+}
+
+/// The fields leading up to a value that failed to encode or decode, outermost first.
+///
+/// Only the innermost [`FieldPath::CAPACITY`] fields are kept.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct FieldPath {
+    // Stored innermost first, since that's the order they're added in.
+    fields: [&'static str; Self::CAPACITY],
+    len: u8,
+    truncated: bool,
+}
+
+impl FieldPath {
+    /// The maximum number of fields that are kept.
+    pub const CAPACITY: usize = 4;
+
+    fn push(&mut self, field: &'static str) {
+        if self.len() == Self::CAPACITY {
+            self.truncated = true;
+        } else {
+            self.fields[self.len()] = field;
+            self.len += 1;
+        }
+    }
+
+    /// Returns the number of recorded fields.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns true if no fields have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the recorded fields, outermost first.
+    pub fn iter(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.fields[..self.len()].iter().rev().copied()
+    }
+}
+
+impl fmt::Debug for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{self}\"")
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.truncated {
+            write!(f, "... -> ")?;
+        }
+
+        for (i, field) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{field}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! Implementations for the fixed capacity collections in [heapless](https://crates.io/crates/heapless).
+//!
+//! These use the same wire format as their `alloc` counterparts, so a `heapless::Vec<T, N>` can
+//! be decoded as a `Vec<T>` and vice versa. Decoding fails if the length exceeds the capacity.
+
+use crate::{
+    Buffin, Error, ErrorKind, FromBytes, Limits, Result, ToBytes,
+    len_prefix::{self, Sequence, SequenceFromBytes},
+    max_size,
+};
+use core::hash::{BuildHasher, Hash};
+use heapless::{IndexMap, String, Vec};
+use nom::{IResult, number::streaming::le_u32};
+
+/// Fails if `len` exceeds `capacity`. `input` is where the length prefix starts.
+fn check_capacity(len: usize, capacity: usize, input: &[u8]) -> Result<(), nom::Err<Error>> {
+    if len > capacity {
+        return Err(nom::Err::Failure(Error::at_input(
+            ErrorKind::LengthLimitExceeded {
+                len,
+                limit: capacity,
+            },
+            input,
+        )));
+    }
+
+    Ok(())
+}
+
+impl<T: ToBytes, const N: usize> ToBytes for Vec<T, N> {
+    const MAX_SIZE: Option<usize> = max_size::sum(Some(4), max_size::times(T::MAX_SIZE, N));
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_slice().to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.as_slice().encoded_len()
+    }
+}
+
+impl<'de, T: FromBytes<'de>, const N: usize> FromBytes<'de> for Vec<T, N> {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        len_prefix::from_bytes::<u32, _>(buffer, limits)
+    }
+}
+
+impl<T: ToBytes, const N: usize> Sequence for Vec<T, N> {
+    const MAX_ITEMS_SIZE: Option<usize> = max_size::times(T::MAX_SIZE, N);
+
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_slice().items_to_bytes(buffer)
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        self.as_slice().items_encoded_len()
+    }
+}
+
+impl<'de, T: FromBytes<'de>, const N: usize> SequenceFromBytes<'de> for Vec<T, N> {
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error> {
+        check_capacity(len, N, prefix)?;
+        limits.check_collection_len(len, prefix)?;
+        let limits = limits.nested(prefix)?;
+        let mut input = buffer;
+        let mut result = Vec::new();
+
+        for _ in 0..len {
+            let (b, it) = T::from_bytes_limited(input, limits)?;
+            // The length has been checked against the capacity already.
+            let _ = result.push(it);
+            input = b;
+        }
+
+        Ok((input, result))
+    }
+}
+
+impl<const N: usize> ToBytes for String<N> {
+    const MAX_SIZE: Option<usize> = Some(4 + N);
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_str().to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.as_str().encoded_len()
+    }
+}
+
+impl<const N: usize> FromBytes<'_> for String<N> {
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &[u8], limits: Limits) -> IResult<&[u8], Self, Error> {
+        len_prefix::from_bytes::<u32, _>(buffer, limits)
+    }
+}
+
+impl<const N: usize> Sequence for String<N> {
+    const MAX_ITEMS_SIZE: Option<usize> = Some(N);
+
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_str().items_to_bytes(buffer)
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<'de, const N: usize> SequenceFromBytes<'de> for String<N> {
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error> {
+        check_capacity(len, N, prefix)?;
+        let (buffer, s) = <&str>::items_from_bytes(buffer, len, limits, prefix)?;
+
+        let mut result = String::new();
+        // The length has been checked against the capacity already.
+        let _ = result.push_str(s);
+
+        Ok((buffer, result))
+    }
+}
+
+impl<K, V, S, const N: usize> ToBytes for IndexMap<K, V, S, N>
+where
+    K: ToBytes,
+    V: ToBytes,
+{
+    const MAX_SIZE: Option<usize> = max_size::sum(
+        Some(4),
+        max_size::times(max_size::sum(K::MAX_SIZE, V::MAX_SIZE), N),
+    );
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        let mut buffer = Buffin::new(buffer);
+
+        buffer.add(&(self.len() as u32))?;
+        for (key, value) in self.iter() {
+            buffer.add(key)?;
+            buffer.add(value)?;
+        }
+
+        Ok(buffer.len())
+    }
+
+    fn encoded_len(&self) -> usize {
+        4 + self
+            .iter()
+            .map(|(key, value)| key.encoded_len() + value.encoded_len())
+            .sum::<usize>()
+    }
+}
+
+impl<'de, K, V, S, const N: usize> FromBytes<'de> for IndexMap<K, V, S, N>
+where
+    K: FromBytes<'de> + Eq + Hash,
+    V: FromBytes<'de>,
+    S: BuildHasher + Default,
+{
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        let (mut input, len) = le_u32(buffer)?;
+        let len = len as usize;
+        check_capacity(len, N, buffer)?;
+        limits.check_collection_len(len, buffer)?;
+        let limits = limits.nested(buffer)?;
+        let mut result = IndexMap::default();
+
+        for _ in 0..len {
+            let (b, key) = K::from_bytes_limited(input, limits)?;
+            let (b, value) = V::from_bytes_limited(b, limits)?;
+            // The length has been checked against the capacity already.
+            let _ = result.insert(key, value);
+            input = b;
+        }
+
+        Ok((input, result))
+    }
+}
@@ -0,0 +1,425 @@
+//! Length prefixes for strings, slices and collections.
+//!
+//! By default, their length is encoded as a `u32`. [`Prefixed`] encodes it with another
+//! [`LenPrefix`] instead, e.g. `Prefixed<u8, String>` for formats that use a single byte. The
+//! derive does the same for a field with `#[buffin(len = "u8")]`.
+//!
+//! ```
+//! use buffin::{FromBytes, ToBytes, len_prefix::Prefixed};
+//!
+//! let name = Prefixed::<u8, &str>::new("attic");
+//! assert_eq!(name.to_vec().unwrap(), b"\x05attic");
+//!
+//! let (_, name) = Prefixed::<u8, &str>::from_bytes(b"\x05attic").unwrap();
+//! assert_eq!(*name, "attic");
+//! ```
+
+use crate::{Buffin, Error, ErrorKind, FromBytes, Limits, Result, ToBytes, max_size};
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+use nom::{
+    IResult, Needed,
+    bytes::streaming::take,
+    number::streaming::{le_u8, le_u16, le_u32},
+};
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+/// How the length of a string or collection is encoded.
+pub trait LenPrefix {
+    /// The largest length the prefix can hold.
+    const MAX_LEN: usize;
+
+    /// The largest number of bytes the prefix takes.
+    const MAX_SIZE: usize;
+
+    /// Encodes `len`, failing with [`ErrorKind::LengthLimitExceeded`] if it doesn't fit.
+    fn to_bytes(len: usize, buffer: &mut [u8]) -> Result<usize>;
+
+    /// Returns the number of bytes `to_bytes` will use for `len`.
+    fn encoded_len(len: usize) -> usize;
+
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], usize, Error>;
+}
+
+macro_rules! impl_len_prefix {
+    ($($ty:ty => $parser:ident),* $(,)?) => {
+        $(
+            impl LenPrefix for $ty {
+                const MAX_LEN: usize = <$ty>::MAX as usize;
+                const MAX_SIZE: usize = size_of::<$ty>();
+
+                fn to_bytes(len: usize, buffer: &mut [u8]) -> Result<usize> {
+                    match <$ty>::try_from(len) {
+                        Ok(len) => len.to_bytes(buffer),
+                        Err(_) => Err(Error::new(ErrorKind::LengthLimitExceeded {
+                            len,
+                            limit: Self::MAX_LEN,
+                        })),
+                    }
+                }
+
+                fn encoded_len(_: usize) -> usize {
+                    size_of::<$ty>()
+                }
+
+                fn from_bytes(buffer: &[u8]) -> IResult<&[u8], usize, Error> {
+                    let (buffer, len) = $parser(buffer)?;
+                    Ok((buffer, len as usize))
+                }
+            }
+        )*
+    };
+}
+
+impl_len_prefix! {
+    u8 => le_u8,
+    u16 => le_u16,
+    u32 => le_u32,
+}
+
+/// A length encoded as a LEB128 varint: seven bits per byte, least significant first, with the
+/// high bit set on every byte but the last.
+///
+/// Lengths below 128 take a single byte.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Varint;
+
+impl LenPrefix for Varint {
+    const MAX_LEN: usize = usize::MAX;
+    const MAX_SIZE: usize = (usize::BITS as usize).div_ceil(7);
+
+    fn to_bytes(len: usize, buffer: &mut [u8]) -> Result<usize> {
+        let mut buffer = Buffin::new(buffer);
+        let mut rest = len;
+
+        while rest >= 0x80 {
+            buffer.add(&((rest as u8) | 0x80))?;
+            rest >>= 7;
+        }
+        buffer.add(&(rest as u8))?;
+
+        Ok(buffer.len())
+    }
+
+    fn encoded_len(len: usize) -> usize {
+        let bits = usize::BITS - len.leading_zeros();
+        (bits as usize).div_ceil(7).max(1)
+    }
+
+    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], usize, Error> {
+        let mut len = 0usize;
+
+        for (i, byte) in buffer.iter().enumerate() {
+            let shift = 7 * i as u32;
+            let bits = (byte & 0x7f) as usize;
+            // Anything that doesn't fit in a usize can't be a length.
+            if shift >= usize::BITS || (bits << shift) >> shift != bits {
+                return Err(nom::Err::Failure(Error::at_input(
+                    ErrorKind::Parse(nom::error::ErrorKind::TooLarge),
+                    buffer,
+                )));
+            }
+
+            len |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok((&buffer[i + 1..], len));
+            }
+        }
+
+        Err(nom::Err::Incomplete(Needed::new(1)))
+    }
+}
+
+/// A string or collection, which is encoded as its length followed by its items.
+///
+/// Implemented for the types that are length prefixed, so that [`Prefixed`] can pick another
+/// prefix for them.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't length prefixed",
+    note = "length prefixes only apply to strings, slices and collections"
+)]
+pub trait Sequence {
+    /// The largest number of bytes the items can be encoded into, if there is one.
+    const MAX_ITEMS_SIZE: Option<usize> = None;
+
+    /// Returns the length that goes in the prefix: the number of items, or bytes for strings.
+    fn item_count(&self) -> usize;
+
+    /// Encodes the items, without the length.
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize>;
+
+    /// Returns the number of bytes `items_to_bytes` will use.
+    fn items_encoded_len(&self) -> usize;
+}
+
+/// A [`Sequence`] that can be decoded once its length is known.
+pub trait SequenceFromBytes<'de>: Sized {
+    /// Decodes `len` items from `buffer`, within the given limits.
+    ///
+    /// `prefix` is where the length prefix starts, and is used for errors about the length.
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error>;
+}
+
+/// Encodes a sequence with the given length prefix.
+pub(crate) fn to_bytes<L: LenPrefix, T: Sequence + ?Sized>(
+    value: &T,
+    buffer: &mut [u8],
+) -> Result<usize> {
+    let mut buffer = Buffin::new(buffer);
+
+    buffer.add_with(|buffer| L::to_bytes(value.item_count(), buffer))?;
+    buffer.add_with(|buffer| value.items_to_bytes(buffer))?;
+
+    Ok(buffer.len())
+}
+
+/// Returns the number of bytes [`to_bytes`] will use.
+pub(crate) fn encoded_len<L: LenPrefix, T: Sequence + ?Sized>(value: &T) -> usize {
+    L::encoded_len(value.item_count()) + value.items_encoded_len()
+}
+
+/// Decodes a sequence with the given length prefix.
+pub(crate) fn from_bytes<'de, L: LenPrefix, T: SequenceFromBytes<'de>>(
+    buffer: &'de [u8],
+    limits: Limits,
+) -> IResult<&'de [u8], T, Error> {
+    let (input, len) = L::from_bytes(buffer)?;
+    T::items_from_bytes(input, len, limits, buffer)
+}
+
+impl<T: Sequence + ?Sized> Sequence for &T {
+    const MAX_ITEMS_SIZE: Option<usize> = T::MAX_ITEMS_SIZE;
+
+    fn item_count(&self) -> usize {
+        (**self).item_count()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        (**self).items_to_bytes(buffer)
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        (**self).items_encoded_len()
+    }
+}
+
+impl Sequence for str {
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        let mut buffer = Buffin::new(buffer);
+        buffer.add_bytes(self.as_bytes())?;
+        Ok(buffer.len())
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<'de: 'a, 'a> SequenceFromBytes<'de> for &'a str {
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error> {
+        limits.check_string_len(len, prefix)?;
+        let (rest, bytes) = take(len)(buffer)?;
+        match core::str::from_utf8(bytes) {
+            Ok(s) => Ok((rest, s)),
+            Err(_) => Err(nom::Err::Failure(Error::at_input(
+                ErrorKind::InvalidUtf8,
+                buffer,
+            ))),
+        }
+    }
+}
+
+impl<T: ToBytes> Sequence for [T] {
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        T::slice_to_bytes(self, buffer)
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        self.iter().map(T::encoded_len).sum()
+    }
+}
+
+impl<'de: 'a, 'a> SequenceFromBytes<'de> for &'a [u8] {
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error> {
+        limits.check_collection_len(len, prefix)?;
+        take(len)(buffer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Sequence for String {
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_str().items_to_bytes(buffer)
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> SequenceFromBytes<'de> for String {
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error> {
+        let (buffer, s) = <&str>::items_from_bytes(buffer, len, limits, prefix)?;
+        Ok((buffer, String::from(s)))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Sequence for Cow<'_, str> {
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_ref().items_to_bytes(buffer)
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de: 'a, 'a> SequenceFromBytes<'de> for Cow<'a, str> {
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error> {
+        let (buffer, s) = <&str>::items_from_bytes(buffer, len, limits, prefix)?;
+        Ok((buffer, Cow::Borrowed(s)))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: ToBytes> Sequence for Vec<T> {
+    fn item_count(&self) -> usize {
+        self.len()
+    }
+
+    fn items_to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.as_slice().items_to_bytes(buffer)
+    }
+
+    fn items_encoded_len(&self) -> usize {
+        self.as_slice().items_encoded_len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, T: FromBytes<'de>> SequenceFromBytes<'de> for Vec<T> {
+    fn items_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+        prefix: &'de [u8],
+    ) -> IResult<&'de [u8], Self, Error> {
+        limits.check_collection_len(len, prefix)?;
+        T::vec_from_bytes(buffer, len, limits.nested(prefix)?)
+    }
+}
+
+/// A string or collection whose length is encoded with `L` rather than a `u32`.
+///
+/// It derefs to the value, and decodes like it, so e.g. `Prefixed<u16, Vec<u8>>` decodes anything
+/// a `Vec<u8>` does, but reads a 2 byte length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Prefixed<L, T> {
+    value: T,
+    prefix: PhantomData<L>,
+}
+
+impl<L, T> Prefixed<L, T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            prefix: PhantomData,
+        }
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<L, T> From<T> for Prefixed<L, T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<L, T> Deref for Prefixed<L, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<L, T> DerefMut for Prefixed<L, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<L: LenPrefix, T: Sequence> ToBytes for Prefixed<L, T> {
+    const MAX_SIZE: Option<usize> = max_size::sum(Some(L::MAX_SIZE), T::MAX_ITEMS_SIZE);
+
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize> {
+        to_bytes::<L, T>(&self.value, buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        encoded_len::<L, T>(&self.value)
+    }
+}
+
+impl<'de, L: LenPrefix, T: SequenceFromBytes<'de>> FromBytes<'de> for Prefixed<L, T> {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error> {
+        Self::from_bytes_limited(buffer, Limits::global())
+    }
+
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        let (buffer, value) = from_bytes::<L, T>(buffer, limits)?;
+        Ok((buffer, Self::new(value)))
+    }
+}
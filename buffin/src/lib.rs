@@ -1,14 +1,45 @@
-#![cfg_attr(feature = "no_std", no_std)]
+#![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "tracing")]
 use core::any::type_name;
-use eyre::{Result, bail};
-use nom::IResult;
-use tracing::warn;
+use core::num::NonZeroUsize;
+use nom::{IResult, Needed};
 
+#[doc(hidden)]
+pub mod __private;
 pub mod basic_types;
+#[cfg(feature = "alloc")]
+mod buffin_vec;
+mod error;
+#[cfg(feature = "heapless")]
+pub mod heapless_types;
+pub mod len_prefix;
+mod limits;
+pub mod max_size;
+
+#[cfg(feature = "alloc")]
+pub use buffin_vec::BuffinVec;
+pub use error::{Error, ErrorKind, FieldPath, SeenBytes};
+pub use limits::Limits;
+
+/// A `Result` defaulting to [`buffin::Error`](Error).
+pub type Result<T, E = Error> = core::result::Result<T, E>;
 
+/// A buffer that values can be added to and popped from.
+///
+/// Popping doesn't move the remaining bytes, it just moves past the popped ones. Those bytes are
+/// reclaimed once the buffer is empty, or when something is added that doesn't fit at the end,
+/// at which point the remaining bytes are moved to the front in one go.
 pub struct Buffin<'a> {
     buffer: &'a mut [u8],
+    // The used bytes are `buffer[head..pos]`.
+    head: usize,
     pos: usize,
 }
 
@@ -16,32 +47,45 @@ pub struct Buffin<'a> {
 impl<'a> Buffin<'a> {
     /// Create a new instance with an empty buffer.
     pub fn new(buffer: &'a mut [u8]) -> Self {
-        Self { buffer, pos: 0 }
+        Self {
+            buffer,
+            head: 0,
+            pos: 0,
+        }
     }
 
     /// Create a new instance that considers itself filled by the provided buffer.
     pub fn new_filled(buffer: &'a mut [u8]) -> Self {
         let len = buffer.len();
-        Self { buffer, pos: len }
+        Self {
+            buffer,
+            head: 0,
+            pos: len,
+        }
     }
 
     /// Create a new instance that considers the first `pos` bytes as data.
     pub fn with_pos(buffer: &'a mut [u8], pos: usize) -> Self {
-        Self { buffer, pos }
+        Self {
+            buffer,
+            head: 0,
+            pos,
+        }
     }
 
     /// Returns the number of used bytes.
     pub fn len(&self) -> usize {
-        self.pos
+        self.pos - self.head
     }
 
     /// Returns the used bytes as a slice.
-    pub fn bytes(&'a self) -> &'a [u8] {
-        &self.buffer[..self.pos]
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer[self.head..self.pos]
     }
 
     /// Empty the buffer.
     pub fn clear(&mut self) {
+        self.head = 0;
         self.pos = 0;
     }
 
@@ -53,58 +97,232 @@ impl<'a> Buffin<'a> {
 
     /// Adds the given bytes as is.
     pub fn add_bytes(&mut self, bytes: &[u8]) -> Result<()> {
-        if self.pos + bytes.len() >= self.buffer.len() {
-            bail!("Buffer is too small");
-        }
-
-        self.buffer[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
-        self.pos += bytes.len();
-
+        self.claim(bytes.len())?.copy_from_slice(bytes);
         Ok(())
     }
 
     /// Adds something that implements ToBytes.
     pub fn add<T: ToBytes>(&mut self, b: &T) -> Result<()> {
-        self.pos += b.to_bytes(&mut self.buffer[self.pos..])?;
+        // The encoded length only matters when there's something to compact, and computing it
+        // can mean walking the whole value.
+        if self.head > 0 {
+            self.make_room(b.encoded_len());
+        }
+        self.add_with(|buffer| b.to_bytes(buffer))
+    }
+
+    /// Marks the next `n` bytes as used, and returns them so they can be filled in.
+    pub(crate) fn claim(&mut self, n: usize) -> Result<&mut [u8]> {
+        self.make_room(n);
+
+        if n > self.buffer.len() - self.pos {
+            return Err(Error::new(ErrorKind::BufferFull {
+                requested: n,
+                available: self.buffer.len() - self.len(),
+            })
+            .offset_by(self.len()));
+        }
+
+        let start = self.pos;
+        self.pos += n;
+
+        Ok(&mut self.buffer[start..self.pos])
+    }
+
+    /// Lets `f` write into the unused part of the buffer, and marks as many bytes as it reports
+    /// as used.
+    pub(crate) fn add_with(&mut self, f: impl FnOnce(&mut [u8]) -> Result<usize>) -> Result<()> {
+        let len = self.len();
+        self.pos += f(&mut self.buffer[self.pos..]).map_err(|err| err.offset_by(len))?;
         Ok(())
     }
 
+    /// Moves the used bytes to the front of the buffer, if that's what it takes to fit `n` more.
+    fn make_room(&mut self, n: usize) {
+        if self.head > 0 && n > self.buffer.len() - self.pos {
+            self.buffer.copy_within(self.head..self.pos, 0);
+            self.pos -= self.head;
+            self.head = 0;
+        }
+    }
+
     /// Remove the n first bytes.
     pub fn remove_first(&mut self, n: usize) {
-        self.buffer.copy_within(n..self.pos, 0);
-        self.pos -= n;
-    }
-
-    /// Attempts to pop the first item of the given type.
-    pub fn pop<T: FromBytes>(&mut self) -> Result<T, PopFailure> {
-        match T::from_bytes(self.bytes()) {
-            Ok((remainder, result)) => {
-                let removed = self.len() - remainder.len();
-                self.remove_first(removed);
-                Ok(result)
-            }
-            Result::Err(err) => {
-                if err.is_incomplete() {
-                    Err(PopFailure::Incomplete)
-                } else {
-                    warn!(?err, type=?type_name::<T>(), bytes_left=?self.len(), "failed to parse");
-                    Err(PopFailure::Invalid)
-                }
-            }
+        assert!(n <= self.len(), "cannot remove more bytes than are used");
+
+        self.head += n;
+        if self.head == self.pos {
+            self.head = 0;
+            self.pos = 0;
         }
     }
+
+    /// Attempts to pop the first item of the given type, within the global [`Limits`].
+    pub fn pop<T: FromBytesOwned>(&mut self) -> Result<T, PopFailure> {
+        self.pop_with(Limits::global())
+    }
+
+    /// Attempts to pop the first item of the given type, within the given limits.
+    pub fn pop_with<T: FromBytesOwned>(&mut self, limits: Limits) -> Result<T, PopFailure> {
+        let (result, removed) = self.peek_with::<T>(limits)?;
+        self.remove_first(removed);
+        Ok(result)
+    }
+
+    /// Attempts to decode the first item of the given type without removing it, within the global
+    /// [`Limits`].
+    ///
+    /// The item may borrow from the buffer. Returns the item along with the number of bytes it
+    /// occupies, which can be passed to [`Buffin::remove_first`] once the item has been dropped.
+    pub fn peek<'b, T: FromBytes<'b>>(&'b self) -> Result<(T, usize), PopFailure> {
+        self.peek_with(Limits::global())
+    }
+
+    /// Like [`Buffin::peek`], but within the given limits.
+    pub fn peek_with<'b, T: FromBytes<'b>>(
+        &'b self,
+        limits: Limits,
+    ) -> Result<(T, usize), PopFailure> {
+        peek_bytes(self.bytes(), limits)
+    }
 }
 
+/// Decodes the first item in `bytes`, returning it along with the number of bytes it occupies.
+pub(crate) fn peek_bytes<'b, T: FromBytes<'b>>(
+    bytes: &'b [u8],
+    limits: Limits,
+) -> Result<(T, usize), PopFailure> {
+    match T::from_bytes_limited(bytes, limits) {
+        Ok((remainder, result)) => Ok((result, bytes.len() - remainder.len())),
+        Err(nom::Err::Incomplete(needed)) => Err(PopFailure::Incomplete {
+            needed: match needed {
+                Needed::Size(n) => Some(n),
+                Needed::Unknown => None,
+            },
+        }),
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+            let err = err.located_in(bytes);
+            #[cfg(feature = "tracing")]
+            tracing::warn!(%err, type=?type_name::<T>(), bytes_left=?bytes.len(), "failed to parse");
+            Err(PopFailure::Invalid(err))
+        }
+    }
+}
+
+/// Why an item couldn't be popped.
 #[derive(Debug)]
 pub enum PopFailure {
-    Invalid,
-    Incomplete,
+    /// The bytes in the buffer aren't a valid item.
+    Invalid(Error),
+    /// The buffer holds the start of an item, but not all of it.
+    ///
+    /// `needed` is the number of bytes it takes to get further, if known. The item may need more
+    /// than that in the end, e.g. once the length of a string has been read, its bytes are needed.
+    Incomplete { needed: Option<NonZeroUsize> },
 }
 
 pub trait ToBytes: Sized {
+    /// The largest number of bytes any value of this type can be encoded into, if there is one.
+    ///
+    /// This can be used to size buffers at compile time, e.g. `[0; Message::MAX_SIZE.unwrap()]`.
+    const MAX_SIZE: Option<usize> = None;
+
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize>;
+
+    /// Returns the number of bytes `to_bytes` will use for this value.
+    fn encoded_len(&self) -> usize;
+
+    /// Encodes the value into a new vector.
+    #[cfg(feature = "alloc")]
+    fn to_vec(&self) -> Result<alloc::vec::Vec<u8>> {
+        let mut buffer = BuffinVec::with_capacity(self.encoded_len());
+        buffer.add(self)?;
+        Ok(buffer.into_vec())
+    }
+
+    /// Encodes the items one after the other, without a length prefix.
+    ///
+    /// Types like `u8` override this to copy the whole slice at once.
+    #[doc(hidden)]
+    fn slice_to_bytes(items: &[Self], buffer: &mut [u8]) -> Result<usize> {
+        let mut buffer = Buffin::new(buffer);
+        for item in items {
+            buffer.add(item)?;
+        }
+        Ok(buffer.len())
+    }
 }
 
-pub trait FromBytes: Sized {
-    fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Self>;
+/// The most memory [`FromBytes::vec_from_bytes`] reserves up front, before any items have been
+/// decoded.
+#[cfg(feature = "alloc")]
+const MAX_PREALLOC_BYTES: usize = 64 * 1024;
+
+/// Decodes a value from the start of a buffer, possibly borrowing from it.
+pub trait FromBytes<'de>: Sized {
+    fn from_bytes(buffer: &'de [u8]) -> IResult<&'de [u8], Self, Error>;
+
+    /// Decodes a value within the given limits.
+    ///
+    /// Types holding lengths or other values override this, check their lengths against the
+    /// limits, and pass [`Limits::nested`] on to what they contain. Their `from_bytes` then calls
+    /// this with [`Limits::global`]. The default ignores the limits.
+    fn from_bytes_limited(buffer: &'de [u8], limits: Limits) -> IResult<&'de [u8], Self, Error> {
+        let _ = limits;
+        Self::from_bytes(buffer)
+    }
+
+    /// Decodes `N` items that were encoded one after the other.
+    ///
+    /// Types like `u8` override this to copy the whole array at once.
+    #[doc(hidden)]
+    fn array_from_bytes<const N: usize>(
+        buffer: &'de [u8],
+        limits: Limits,
+    ) -> IResult<&'de [u8], [Self; N], Error> {
+        let mut buffer = buffer;
+        let mut items = [const { None }; N];
+
+        for item in items.iter_mut() {
+            let (b, it) = Self::from_bytes_limited(buffer, limits)?;
+            *item = Some(it);
+            buffer = b;
+        }
+
+        Ok((
+            buffer,
+            items.map(|item| item.expect("every item has been decoded")),
+        ))
+    }
+
+    /// Decodes `len` items that were encoded one after the other.
+    ///
+    /// Types like `u8` override this to copy all of them at once.
+    #[doc(hidden)]
+    #[cfg(feature = "alloc")]
+    fn vec_from_bytes(
+        buffer: &'de [u8],
+        len: usize,
+        limits: Limits,
+    ) -> IResult<&'de [u8], alloc::vec::Vec<Self>, Error> {
+        let mut buffer = buffer;
+        // Items almost always take up at least a byte, so this avoids trusting a bogus length. Large
+        // items could still add up to a lot of memory, so beyond a point the vector grows as the
+        // items are actually decoded.
+        let max_prealloc = MAX_PREALLOC_BYTES / core::mem::size_of::<Self>().max(1);
+        let mut result = alloc::vec::Vec::with_capacity(len.min(buffer.len()).min(max_prealloc));
+
+        for _ in 0..len {
+            let (b, it) = Self::from_bytes_limited(buffer, limits)?;
+            result.push(it);
+            buffer = b;
+        }
+
+        Ok((buffer, result))
+    }
 }
+
+/// A [`FromBytes`] type that does not borrow from the buffer it was decoded from.
+pub trait FromBytesOwned: for<'de> FromBytes<'de> {}
+
+impl<T> FromBytesOwned for T where T: for<'de> FromBytes<'de> {}
@@ -0,0 +1,143 @@
+use crate::{Error, ErrorKind};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static MAX_COLLECTION_LEN: AtomicUsize = AtomicUsize::new(Limits::DEFAULT.max_collection_len);
+static MAX_STRING_LEN: AtomicUsize = AtomicUsize::new(Limits::DEFAULT.max_string_len);
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(Limits::DEFAULT.max_depth);
+
+/// Limits enforced while decoding, so that a malformed length prefix can't make the decoder wait
+/// for, or allocate, gigabytes.
+///
+/// [`Buffin::pop`](crate::Buffin::pop) uses the global limits, see [`Limits::set_global`], and
+/// [`Buffin::pop_with`](crate::Buffin::pop_with) takes them per call.
+///
+/// ```
+/// use buffin::Limits;
+///
+/// Limits::set_global(
+///     Limits::default()
+///         .with_max_collection_len(1024)
+///         .with_max_string_len(256)
+///         .with_max_depth(8),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    max_collection_len: usize,
+    max_string_len: usize,
+    max_depth: usize,
+    depth: usize,
+}
+
+impl Limits {
+    /// Collections of up to 2^20 items, strings of up to 1 MiB, and a nesting depth of 128.
+    ///
+    /// That's generous for messages, but keeps a bogus length from asking for gigabytes. Formats
+    /// that carry more, or firmware that can't afford as much, should set their own.
+    pub const DEFAULT: Self = Self {
+        max_collection_len: 1 << 20,
+        max_string_len: 1 << 20,
+        max_depth: 128,
+        depth: 0,
+    };
+
+    /// Returns the limits used when none are given.
+    pub fn global() -> Self {
+        Self {
+            max_collection_len: MAX_COLLECTION_LEN.load(Ordering::Relaxed),
+            max_string_len: MAX_STRING_LEN.load(Ordering::Relaxed),
+            max_depth: MAX_DEPTH.load(Ordering::Relaxed),
+            depth: 0,
+        }
+    }
+
+    /// Sets the limits used when none are given.
+    pub fn set_global(limits: Self) {
+        MAX_COLLECTION_LEN.store(limits.max_collection_len, Ordering::Relaxed);
+        MAX_STRING_LEN.store(limits.max_string_len, Ordering::Relaxed);
+        MAX_DEPTH.store(limits.max_depth, Ordering::Relaxed);
+    }
+
+    /// Sets the maximum number of items in a collection, or bytes in a byte slice.
+    pub const fn with_max_collection_len(mut self, len: usize) -> Self {
+        self.max_collection_len = len;
+        self
+    }
+
+    /// Sets the maximum number of bytes in a string.
+    pub const fn with_max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = len;
+        self
+    }
+
+    /// Sets how deeply values may be nested inside each other.
+    pub const fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Returns the maximum number of items in a collection, or bytes in a byte slice.
+    pub const fn max_collection_len(&self) -> usize {
+        self.max_collection_len
+    }
+
+    /// Returns the maximum number of bytes in a string.
+    pub const fn max_string_len(&self) -> usize {
+        self.max_string_len
+    }
+
+    /// Returns how deeply values may be nested inside each other.
+    pub const fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Returns the limits for decoding a value nested one level deeper, failing if that's too deep.
+    ///
+    /// `input` is where the nested value starts, and is used for the error.
+    pub fn nested(self, input: &[u8]) -> Result<Self, nom::Err<Error>> {
+        if self.depth >= self.max_depth {
+            return Err(nom::Err::Failure(Error::at_input(
+                ErrorKind::DepthLimitExceeded {
+                    limit: self.max_depth,
+                },
+                input,
+            )));
+        }
+
+        Ok(Self {
+            depth: self.depth + 1,
+            ..self
+        })
+    }
+
+    /// Fails if a collection of `len` items is too long.
+    ///
+    /// `input` is where the length prefix starts, and is used for the error.
+    pub fn check_collection_len(self, len: usize, input: &[u8]) -> Result<(), nom::Err<Error>> {
+        check_len(len, self.max_collection_len, input)
+    }
+
+    /// Fails if a string of `len` bytes is too long.
+    ///
+    /// `input` is where the length prefix starts, and is used for the error.
+    pub fn check_string_len(self, len: usize, input: &[u8]) -> Result<(), nom::Err<Error>> {
+        check_len(len, self.max_string_len, input)
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+fn check_len(len: usize, limit: usize, input: &[u8]) -> Result<(), nom::Err<Error>> {
+    if len > limit {
+        return Err(nom::Err::Failure(Error::at_input(
+            ErrorKind::LengthLimitExceeded { len, limit },
+            input,
+        )));
+    }
+
+    Ok(())
+}
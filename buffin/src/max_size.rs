@@ -0,0 +1,32 @@
+//! Helpers for combining [`ToBytes::MAX_SIZE`](crate::ToBytes::MAX_SIZE) values in const context.
+
+/// The maximum size of two values encoded one after the other.
+///
+/// Unbounded if either of them is unbounded.
+pub const fn sum(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.checked_add(b),
+        _ => None,
+    }
+}
+
+/// The maximum size of a value that is encoded as either `a` or `b`.
+///
+/// Unbounded if either of them is unbounded.
+pub const fn max(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) if a > b => Some(a),
+        (Some(_), Some(b)) => Some(b),
+        _ => None,
+    }
+}
+
+/// The maximum size of `n` values encoded one after the other.
+///
+/// Unbounded if the value is unbounded.
+pub const fn times(a: Option<usize>, n: usize) -> Option<usize> {
+    match a {
+        Some(a) => a.checked_mul(n),
+        None => None,
+    }
+}
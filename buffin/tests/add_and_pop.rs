@@ -0,0 +1,150 @@
+//! Adding and popping can be interleaved. Popping only moves the start of the used bytes, and
+//! adding moves them back to the front of the buffer when that's what it takes to fit.
+
+use buffin::{Buffin, ErrorKind, ToBytes};
+use std::{cell::Cell, collections::VecDeque};
+
+/// A value that counts how often its encoded length is asked for.
+struct Counted<'a> {
+    value: u32,
+    calls: &'a Cell<usize>,
+}
+
+impl ToBytes for Counted<'_> {
+    fn to_bytes(&self, buffer: &mut [u8]) -> buffin::Result<usize> {
+        self.value.to_bytes(buffer)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.calls.set(self.calls.get() + 1);
+        self.value.encoded_len()
+    }
+}
+
+/// A value in the buffer, of either size.
+enum Value {
+    Small(u8),
+    Large(u32),
+}
+
+#[test]
+fn popping_everything_starts_over() {
+    let mut raw = [0; 8];
+    let mut buffer = Buffin::new(&mut raw);
+
+    for value in 0..10u32 {
+        buffer.add(&value).unwrap();
+        buffer.add(&(value + 100)).unwrap();
+        assert_eq!(buffer.pop::<u32>().unwrap(), value);
+        assert_eq!(buffer.pop::<u32>().unwrap(), value + 100);
+        assert!(buffer.is_empty());
+    }
+}
+
+#[test]
+fn adding_compacts_after_a_pop() {
+    let mut raw = [0; 12];
+    let mut buffer = Buffin::new(&mut raw);
+    buffer.add(&1u32).unwrap();
+    buffer.add(&2u32).unwrap();
+    buffer.add(&3u32).unwrap();
+
+    // There's no room at the end, but the first value has made room at the front.
+    assert_eq!(buffer.pop::<u32>().unwrap(), 1);
+    buffer.add(&4u32).unwrap();
+    assert_eq!(buffer.len(), 12);
+    assert_eq!(buffer.bytes(), (2u32, 3u32, 4u32).to_vec().unwrap());
+
+    assert_eq!(buffer.pop::<u32>().unwrap(), 2);
+    assert_eq!(buffer.pop::<u32>().unwrap(), 3);
+    assert_eq!(buffer.pop::<u32>().unwrap(), 4);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn values_wrap_around_the_end_of_the_buffer() {
+    // Mixing 1 and 4 byte values in 10 bytes keeps the used bytes crossing the end of the buffer,
+    // so the adds alternate between appending and compacting first.
+    let mut raw = [0; 10];
+    let mut buffer = Buffin::new(&mut raw);
+    let mut expected = VecDeque::new();
+
+    for step in 0..200u32 {
+        let added = if step % 3 == 0 {
+            buffer.add(&(step as u8)).map(|()| Value::Small(step as u8))
+        } else {
+            buffer.add(&step).map(|()| Value::Large(step))
+        };
+        match added {
+            Ok(value) => expected.push_back(value),
+            Err(err) => assert!(matches!(err.kind(), ErrorKind::BufferFull { .. })),
+        }
+
+        if step % 2 == 1 {
+            match expected.pop_front() {
+                Some(Value::Small(value)) => assert_eq!(buffer.pop::<u8>().unwrap(), value),
+                Some(Value::Large(value)) => assert_eq!(buffer.pop::<u32>().unwrap(), value),
+                None => assert!(buffer.is_empty()),
+            }
+        }
+    }
+
+    while let Some(value) = expected.pop_front() {
+        match value {
+            Value::Small(value) => assert_eq!(buffer.pop::<u8>().unwrap(), value),
+            Value::Large(value) => assert_eq!(buffer.pop::<u32>().unwrap(), value),
+        }
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn a_full_buffer_stays_intact() {
+    let mut raw = [0; 8];
+    let mut buffer = Buffin::new(&mut raw);
+    buffer.add(&1u32).unwrap();
+    buffer.add(&2u32).unwrap();
+    assert_eq!(buffer.pop::<u32>().unwrap(), 1);
+
+    let err = buffer.add(&3u64).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        ErrorKind::BufferFull {
+            requested: 8,
+            available: 4
+        }
+    );
+    assert_eq!(buffer.pop::<u32>().unwrap(), 2);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn encoded_len_is_only_computed_when_compacting() {
+    let calls = Cell::new(0);
+    let mut raw = [0; 8];
+    let mut buffer = Buffin::new(&mut raw);
+
+    buffer
+        .add(&Counted {
+            value: 1,
+            calls: &calls,
+        })
+        .unwrap();
+    buffer
+        .add(&Counted {
+            value: 2,
+            calls: &calls,
+        })
+        .unwrap();
+    assert_eq!(calls.get(), 0);
+
+    assert_eq!(buffer.pop::<u32>().unwrap(), 1);
+    buffer
+        .add(&Counted {
+            value: 3,
+            calls: &calls,
+        })
+        .unwrap();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(buffer.bytes(), (2u32, 3u32).to_vec().unwrap());
+}
@@ -0,0 +1,82 @@
+//! `BuffinVec` grows to fit whatever is added, and pops like `Buffin`.
+
+use buffin::{BuffinVec, PopFailure, ToBytes};
+
+#[test]
+fn grows_past_its_capacity() {
+    let mut buffer = BuffinVec::with_capacity(4);
+    for value in 0..100u32 {
+        buffer.add(&value).unwrap();
+    }
+    buffer.add(&"and a string".to_string()).unwrap();
+    assert_eq!(buffer.len(), 100 * 4 + 4 + 12);
+
+    for value in 0..100u32 {
+        assert_eq!(buffer.pop::<u32>().unwrap(), value);
+    }
+    assert_eq!(buffer.pop::<String>().unwrap(), "and a string");
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn pops_after_growing_past_popped_bytes() {
+    let mut buffer = BuffinVec::with_capacity(8);
+    buffer.add(&1u32).unwrap();
+    buffer.add(&2u32).unwrap();
+    assert_eq!(buffer.pop::<u32>().unwrap(), 1);
+
+    // Doesn't fit in the capacity, even after dropping the popped bytes.
+    let long = vec![0xabu8; 64];
+    buffer.add(&long).unwrap();
+    assert_eq!(buffer.bytes(), (2u32, &long).to_vec().unwrap());
+
+    assert_eq!(buffer.pop::<u32>().unwrap(), 2);
+    assert_eq!(buffer.pop::<Vec<u8>>().unwrap(), long);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn from_vec() {
+    let bytes = (7u16, "seven").to_vec().unwrap();
+    let mut buffer = BuffinVec::from(bytes.clone());
+    assert_eq!(buffer.bytes(), bytes);
+
+    assert_eq!(buffer.pop::<u16>().unwrap(), 7);
+    buffer.add(&8u16).unwrap();
+    assert_eq!(buffer.pop::<String>().unwrap(), "seven");
+    assert_eq!(buffer.pop::<u16>().unwrap(), 8);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn incomplete_values_wait_for_more_bytes() {
+    let bytes = 0x0102_0304u32.to_vec().unwrap();
+    let mut buffer = BuffinVec::from(bytes[..3].to_vec());
+    assert!(matches!(
+        buffer.pop::<u32>(),
+        Err(PopFailure::Incomplete { needed: Some(_) })
+    ));
+    assert_eq!(buffer.len(), 3);
+
+    buffer.add_bytes(&bytes[3..]);
+    assert_eq!(buffer.pop::<u32>().unwrap(), 0x0102_0304);
+}
+
+#[test]
+fn into_vec_drops_popped_bytes() {
+    let mut buffer = BuffinVec::new();
+    buffer.add(&1u8).unwrap();
+    buffer.add(&2u8).unwrap();
+    buffer.add(&3u8).unwrap();
+    assert_eq!(buffer.pop::<u8>().unwrap(), 1);
+
+    assert_eq!(buffer.into_vec(), [2, 3]);
+}
+
+#[test]
+fn matches_to_vec() {
+    let value = (1u8, "two", vec![3u16]);
+    let mut buffer = BuffinVec::new();
+    buffer.add(&value).unwrap();
+    assert_eq!(buffer.into_vec(), value.to_vec().unwrap());
+}
@@ -0,0 +1,10 @@
+//! Helpers shared by the tests.
+
+use buffin::FromBytesOwned;
+
+/// Decodes a `T` from `bytes`, which must hold exactly one.
+pub fn decode<T: FromBytesOwned>(bytes: &[u8]) -> T {
+    let (rest, value) = T::from_bytes(bytes).unwrap();
+    assert!(rest.is_empty());
+    value
+}
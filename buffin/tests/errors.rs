@@ -0,0 +1,41 @@
+//! Decoding errors know their offset once they've been located in the buffer, and not before.
+
+use buffin::{Buffin, ErrorKind, FromBytes, PopFailure, ToBytes};
+
+const BYTES: [u8; 6] = [1, 1, 7, 9, 9, 9];
+
+#[test]
+fn from_bytes_leaves_errors_unlocated() {
+    let Err(nom::Err::Failure(err) | nom::Err::Error(err)) =
+        <(bool, bool, bool)>::from_bytes(&BYTES)
+    else {
+        panic!("expected the third bool to be invalid");
+    };
+
+    assert_eq!(err.kind(), ErrorKind::InvalidValue { value: 7 });
+    assert_eq!(err.offset(), None);
+    assert_eq!(err.to_string(), "invalid value 7");
+
+    let err = err.located_in(&BYTES);
+    assert_eq!(err.offset(), Some(2));
+    assert_eq!(err.to_string(), "invalid value 7 at offset 2");
+}
+
+#[test]
+fn pop_locates_errors() {
+    let mut raw = BYTES;
+    let mut buffer = Buffin::new_filled(&mut raw);
+    let Err(PopFailure::Invalid(err)) = buffer.pop::<(bool, bool, bool)>() else {
+        panic!("expected the third bool to be invalid");
+    };
+
+    assert_eq!(err.offset(), Some(2));
+    assert_eq!(err.to_string(), "invalid value 7 at offset 2");
+}
+
+#[test]
+fn encoding_errors_are_located() {
+    let err = 1u32.to_bytes(&mut [0; 2]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BufferFull { .. }));
+    assert_eq!(err.offset(), Some(0));
+}
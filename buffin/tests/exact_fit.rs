@@ -0,0 +1,147 @@
+//! Every built-in type must encode into a buffer of exactly its encoded length, and fail cleanly
+//! with one byte less.
+
+use buffin::{Buffin, ErrorKind, FromBytes, ToBytes};
+use heapless::index_map::FnvIndexMap;
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    num::{NonZeroI32, NonZeroU8, NonZeroU64},
+    path::PathBuf,
+};
+
+/// Encodes `value` into a buffer of exactly `encoded_len` bytes, checks that one byte less
+/// isn't enough, and returns the encoded bytes.
+fn encode_exactly<T: ToBytes>(value: &T) -> Vec<u8> {
+    let len = value.encoded_len();
+
+    let mut exact = vec![0; len];
+    let mut buffer = Buffin::new(&mut exact);
+    buffer.add(value).expect("value should fit exactly");
+    assert_eq!(buffer.len(), len);
+    assert_eq!(
+        value
+            .to_bytes(&mut exact)
+            .expect("value should fit exactly"),
+        len
+    );
+
+    let mut short = vec![0; len - 1];
+    let mut buffer = Buffin::new(&mut short);
+    let err = buffer.add(value).expect_err("value should not fit");
+    assert!(
+        matches!(err.kind(), ErrorKind::BufferFull { .. }),
+        "unexpected error: {err}"
+    );
+    assert!(buffer.is_empty());
+
+    exact
+}
+
+/// Checks that `bytes` decode back into `expected`, with nothing left over.
+fn assert_decodes<'de, T: FromBytes<'de> + PartialEq + Debug>(bytes: &'de [u8], expected: &T) {
+    let (remainder, decoded) = T::from_bytes(bytes).expect("value should decode");
+    assert!(remainder.is_empty());
+    assert_eq!(&decoded, expected);
+}
+
+macro_rules! exact_fit {
+    ($($name:ident: $ty:ty = $value:expr;)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let value: $ty = $value;
+                let bytes = encode_exactly(&value);
+                assert_decodes::<$ty>(&bytes, &value);
+            }
+        )*
+    };
+}
+
+exact_fit! {
+    u8: u8 = 0xab;
+    u16: u16 = 0xabcd;
+    u32: u32 = 0xdead_beef;
+    u64: u64 = u64::MAX - 1;
+    u128: u128 = u128::MAX - 1;
+    i8: i8 = -5;
+    i16: i16 = -500;
+    i32: i32 = -50_000;
+    i64: i64 = i64::MIN;
+    i128: i128 = i128::MIN;
+    f32: f32 = 1.5;
+    f64: f64 = -2.25;
+    bool: bool = true;
+    char: char = 'ß';
+    non_zero_u8: NonZeroU8 = NonZeroU8::new(7).unwrap();
+    non_zero_u64: NonZeroU64 = NonZeroU64::new(1 << 40).unwrap();
+    non_zero_i32: NonZeroI32 = NonZeroI32::new(-3).unwrap();
+    str: &str = "hello";
+    empty_str: &str = "";
+    string: String = "hello".to_string();
+    cow_str: Cow<str> = Cow::Borrowed("hello");
+    byte_slice: &[u8] = &[1, 2, 3];
+    byte_vec: Vec<u8> = vec![1, 2, 3, 4];
+    empty_vec: Vec<u8> = vec![];
+    number_vec: Vec<u16> = vec![1, 2, 3];
+    string_vec: Vec<String> = vec!["a".to_string(), "bc".to_string()];
+    byte_array: [u8; 4] = [1, 2, 3, 4];
+    number_array: [u32; 3] = [1, 2, 3];
+    tuple: (u8, u32, bool) = (1, 2, true);
+    range: std::ops::RangeInclusive<u16> = 5..=22;
+    some: Option<u32> = Some(7);
+    none: Option<u32> = None;
+    boxed: Box<u64> = Box::new(9);
+    path: PathBuf = PathBuf::from("/tmp/buffin");
+    heapless_vec: heapless::Vec<u16, 4> = heapless::Vec::from_slice(&[1, 2]).unwrap();
+    heapless_string: heapless::String<8> = heapless::String::try_from("hi").unwrap();
+    heapless_index_map: FnvIndexMap<u8, u16, 4> = [(1, 10), (2, 20)].into_iter().collect();
+}
+
+#[test]
+fn slice_of_numbers() {
+    let value: &[u32] = &[1, 2, 3];
+    let bytes = encode_exactly(&value);
+    assert_decodes::<Vec<u32>>(&bytes, &value.to_vec());
+}
+
+#[test]
+fn add_bytes_fills_the_buffer() {
+    let mut raw = [0; 4];
+    let mut buffer = Buffin::new(&mut raw);
+    buffer
+        .add_bytes(&[1, 2, 3, 4])
+        .expect("bytes should fit exactly");
+    assert_eq!(buffer.bytes(), &[1, 2, 3, 4]);
+}
+
+#[test]
+fn add_bytes_reports_requested_and_available() {
+    let mut raw = [0; 4];
+    let mut buffer = Buffin::new(&mut raw);
+    buffer.add_bytes(&[1]).unwrap();
+
+    let err = buffer.add_bytes(&[2, 3, 4, 5]).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        ErrorKind::BufferFull {
+            requested: 4,
+            available: 3
+        }
+    );
+    assert_eq!(err.offset(), Some(1));
+    assert_eq!(buffer.bytes(), &[1]);
+}
+
+#[test]
+fn popped_bytes_are_reclaimed_for_an_exact_fit() {
+    let mut raw = [0; 8];
+    let mut buffer = Buffin::new(&mut raw);
+    buffer.add(&1u32).unwrap();
+    buffer.add(&2u32).unwrap();
+    assert_eq!(buffer.pop::<u32>().unwrap(), 1);
+
+    buffer.add(&3u32).expect("popped bytes should be reused");
+    assert_eq!(buffer.pop::<u32>().unwrap(), 2);
+    assert_eq!(buffer.pop::<u32>().unwrap(), 3);
+}
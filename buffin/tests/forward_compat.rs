@@ -0,0 +1,137 @@
+//! Length-delimited enums let older decoders skip, or forward, variants they don't know.
+
+use buffin::{Buffin, ErrorKind, FromBytes, PopFailure, ToBytes};
+use buffin_derive::{FromBytes, ToBytes};
+
+/// What a newer device sends.
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum NewMessage {
+    Ping,
+    Say {
+        channel: String,
+        message: String,
+        urgent: bool,
+    },
+    Reboot {
+        delay: u32,
+    },
+}
+
+/// What an older host understands.
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum OldMessage {
+    Ping,
+    Say {
+        channel: String,
+        message: String,
+    },
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+}
+
+#[test]
+fn round_trip() {
+    for message in [
+        OldMessage::Ping,
+        OldMessage::Say {
+            channel: "general".to_string(),
+            message: "hi".to_string(),
+        },
+        OldMessage::Unknown(7, vec![1, 2, 3]),
+    ] {
+        let bytes = message.to_vec().unwrap();
+        assert_eq!(bytes.len(), message.encoded_len());
+
+        let (rest, decoded) = OldMessage::from_bytes(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(decoded, message);
+    }
+}
+
+#[test]
+fn known_variants_skip_new_fields() {
+    let bytes = NewMessage::Say {
+        channel: "general".to_string(),
+        message: "hi".to_string(),
+        urgent: true,
+    }
+    .to_vec()
+    .unwrap();
+
+    let (rest, decoded) = OldMessage::from_bytes(&bytes).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(
+        decoded,
+        OldMessage::Say {
+            channel: "general".to_string(),
+            message: "hi".to_string(),
+        }
+    );
+}
+
+#[test]
+fn unknown_variants_are_forwarded_unchanged() {
+    let reboot = NewMessage::Reboot { delay: 500 };
+    let bytes = reboot.to_vec().unwrap();
+
+    let (_, decoded) = OldMessage::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded, OldMessage::Unknown(2, 500u32.to_vec().unwrap()));
+    assert_eq!(decoded.to_vec().unwrap(), bytes);
+
+    let (_, forwarded) = NewMessage::from_bytes(&decoded.to_vec().unwrap()).unwrap();
+    assert_eq!(forwarded, reboot);
+}
+
+#[test]
+fn unknown_variants_dont_block_the_buffer() {
+    let mut raw = [0; 256];
+    let mut buffer = Buffin::new(&mut raw);
+    buffer.add(&NewMessage::Ping).unwrap();
+    buffer.add(&NewMessage::Reboot { delay: 1 }).unwrap();
+    buffer.add(&NewMessage::Ping).unwrap();
+
+    assert_eq!(buffer.pop::<OldMessage>().unwrap(), OldMessage::Ping);
+    assert!(matches!(
+        buffer.pop::<OldMessage>().unwrap(),
+        OldMessage::Unknown(2, _)
+    ));
+    assert_eq!(buffer.pop::<OldMessage>().unwrap(), OldMessage::Ping);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn partial_payloads_are_incomplete() {
+    let bytes = NewMessage::Reboot { delay: 1 }.to_vec().unwrap();
+
+    for len in 0..bytes.len() {
+        let mut prefix = bytes[..len].to_vec();
+        let mut buffer = Buffin::new_filled(&mut prefix);
+        assert!(
+            matches!(
+                buffer.pop::<OldMessage>(),
+                Err(PopFailure::Incomplete { .. })
+            ),
+            "expected Incomplete with {len} of {} bytes",
+            bytes.len()
+        );
+    }
+}
+
+#[test]
+fn payload_too_short_for_its_fields() {
+    // The `Say` tag, with a two byte payload that can't hold a string, followed by a `Ping`.
+    let mut bytes = (1u8, vec![0u8, 0]).to_vec().unwrap();
+    let payload_end = bytes.len();
+    bytes.push(0);
+    bytes.extend(0u32.to_vec().unwrap());
+
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    let Err(PopFailure::Invalid(err)) = buffer.pop::<OldMessage>() else {
+        panic!("expected an invalid payload");
+    };
+    assert_eq!(err.kind(), ErrorKind::Parse(nom::error::ErrorKind::Eof));
+    assert_eq!(err.offset(), Some(payload_end));
+    assert_eq!(err.path().to_string(), "OldMessage::Say.channel");
+}
@@ -0,0 +1,132 @@
+//! Generic types derive with inferred bounds, or with bounds given in `#[buffin(bound)]`.
+
+use buffin::{FromBytes, ToBytes};
+use buffin_derive::{FromBytes, ToBytes};
+use std::{fmt::Debug, marker::PhantomData};
+
+/// Encodes `value`, checks its encoded length, and decodes it again, with nothing left over.
+fn round_trip<'de, T>(value: &T, bytes: &'de mut Vec<u8>) -> T
+where
+    T: ToBytes + FromBytes<'de> + PartialEq + Debug,
+{
+    *bytes = value.to_vec().unwrap();
+    assert_eq!(value.encoded_len(), bytes.len());
+    let (rest, decoded) = T::from_bytes(bytes).unwrap();
+    assert!(rest.is_empty());
+    decoded
+}
+
+/// Only used as a type-level marker, so it implements neither trait.
+#[derive(Debug, PartialEq)]
+struct Celsius;
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(bound = "")]
+struct Reading<U> {
+    value: i16,
+    #[buffin(skip)]
+    unit: PhantomData<U>,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(bound(to_bytes = "T: ToBytes", from_bytes = "T: FromBytes<'de> + Default"))]
+struct Defaulting<T> {
+    value: T,
+    #[buffin(skip)]
+    fallback: T,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Frame<T, const N: usize>
+where
+    T: Copy,
+{
+    id: u8,
+    items: [T; N],
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Named<'a, T> {
+    name: &'a str,
+    value: T,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum Either<'a, L, R> {
+    Left(L),
+    Right { value: R, label: &'a str },
+}
+
+#[test]
+fn bound_override_for_a_marker() {
+    let reading = Reading::<Celsius> {
+        value: -40,
+        unit: PhantomData,
+    };
+    let mut bytes = Vec::new();
+    assert_eq!(round_trip(&reading, &mut bytes), reading);
+    assert_eq!(bytes, (-40i16).to_vec().unwrap());
+}
+
+#[test]
+fn bound_override_per_direction() {
+    let value = Defaulting {
+        value: 5u32,
+        fallback: 9,
+    };
+    let mut bytes = Vec::new();
+    assert_eq!(
+        round_trip(&value, &mut bytes),
+        Defaulting {
+            value: 5,
+            fallback: 0
+        }
+    );
+}
+
+#[test]
+fn const_generics_and_where_clause() {
+    let frame = Frame {
+        id: 3,
+        items: [1u16, 2, 3, 4],
+    };
+    let mut bytes = Vec::new();
+    assert_eq!(round_trip(&frame, &mut bytes), frame);
+    assert_eq!(Frame::<u16, 4>::MAX_SIZE, Some(1 + 8));
+
+    let empty: Frame<u8, 0> = Frame { id: 0, items: [] };
+    assert_eq!(round_trip(&empty, &mut bytes), empty);
+}
+
+#[test]
+fn lifetime_and_type_parameter() {
+    let named = Named {
+        name: "answer",
+        value: 42u64,
+    };
+    let mut bytes = Vec::new();
+    let decoded = round_trip(&named, &mut bytes);
+    assert_eq!(decoded, named);
+
+    let nested = Named {
+        name: "outer",
+        value: Named {
+            name: "inner",
+            value: vec![1i8, -1],
+        },
+    };
+    assert_eq!(round_trip(&nested, &mut bytes), nested);
+}
+
+#[test]
+fn generic_enum() {
+    let mut bytes = Vec::new();
+    let left: Either<u8, String> = Either::Left(1);
+    assert_eq!(round_trip(&left, &mut bytes), left);
+
+    let right: Either<u8, String> = Either::Right {
+        value: "r".to_string(),
+        label: "l",
+    };
+    assert_eq!(round_trip(&right, &mut bytes), right);
+}
@@ -0,0 +1,43 @@
+//! The global limits are shared by the whole process, so they get a test binary of their own.
+
+use buffin::{Buffin, ErrorKind, Limits, PopFailure, ToBytes};
+
+#[test]
+fn global_limits_apply_to_pop() {
+    assert_eq!(Limits::global(), Limits::DEFAULT);
+
+    let mut raw = ("abc", vec![1u8, 2]).to_vec().unwrap();
+    let len = raw.len();
+    let mut buffer = Buffin::new_filled(&mut raw);
+
+    Limits::set_global(
+        Limits::default()
+            .with_max_string_len(2)
+            .with_max_collection_len(8)
+            .with_max_depth(4),
+    );
+    let global = Limits::global();
+    assert_eq!(global.max_string_len(), 2);
+    assert_eq!(global.max_collection_len(), 8);
+    assert_eq!(global.max_depth(), 4);
+
+    let Err(PopFailure::Invalid(err)) = buffer.pop::<(String, Vec<u8>)>() else {
+        panic!("expected the global string limit to apply");
+    };
+    assert_eq!(
+        err.kind(),
+        ErrorKind::LengthLimitExceeded { len: 3, limit: 2 }
+    );
+    assert_eq!(buffer.len(), len);
+
+    // pop_with ignores the global limits.
+    assert_eq!(
+        buffer
+            .pop_with::<(String, Vec<u8>)>(Limits::default())
+            .unwrap(),
+        ("abc".to_string(), vec![1, 2])
+    );
+
+    Limits::set_global(Limits::DEFAULT);
+    assert_eq!(Limits::global(), Limits::DEFAULT);
+}
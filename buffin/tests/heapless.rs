@@ -0,0 +1,88 @@
+//! The heapless collections share their wire format with the `alloc` ones, and refuse lengths
+//! beyond their capacity.
+
+mod common;
+
+use buffin::{Buffin, ErrorKind, FromBytesOwned, PopFailure, ToBytes};
+use heapless::index_map::FnvIndexMap;
+use std::fmt::Debug;
+
+/// Encodes `value` and decodes it as a `U`.
+fn cross_decode<T: ToBytes, U: FromBytesOwned>(value: &T) -> U {
+    common::decode(&value.to_vec().unwrap())
+}
+
+/// Encodes `value`, pops it as a `U` that can't hold it, and checks that the error points at the
+/// length prefix.
+fn assert_over_capacity<T: ToBytes, U: FromBytesOwned + Debug>(
+    value: &T,
+    len: usize,
+    limit: usize,
+) {
+    let mut raw = value.to_vec().unwrap();
+    let mut buffer = Buffin::new_filled(&mut raw);
+
+    let Err(PopFailure::Invalid(err)) = buffer.pop::<U>() else {
+        panic!("expected the length to exceed the capacity");
+    };
+    assert_eq!(err.kind(), ErrorKind::LengthLimitExceeded { len, limit });
+    assert_eq!(err.offset(), Some(0));
+}
+
+#[test]
+fn vec_over_capacity() {
+    assert_over_capacity::<_, heapless::Vec<u16, 3>>(&vec![1u16, 2, 3, 4], 4, 3);
+}
+
+#[test]
+fn string_over_capacity() {
+    assert_over_capacity::<_, heapless::String<4>>(&"hello", 5, 4);
+}
+
+#[test]
+fn index_map_over_capacity() {
+    let pairs = vec![(1u8, 10u16), (2, 20), (3, 30), (4, 40), (5, 50)];
+    assert_over_capacity::<_, FnvIndexMap<u8, u16, 4>>(&pairs, 5, 4);
+}
+
+#[test]
+fn full_capacity_fits() {
+    let vec: heapless::Vec<u16, 3> = cross_decode(&vec![1u16, 2, 3]);
+    assert_eq!(vec, [1, 2, 3]);
+
+    let string: heapless::String<5> = cross_decode(&"hello");
+    assert_eq!(string, "hello");
+}
+
+#[test]
+fn vec_cross_decodes() {
+    let vec = vec![-1i32, 0, 7];
+    let fixed: heapless::Vec<i32, 8> = cross_decode(&vec);
+    assert_eq!(fixed, vec[..]);
+
+    let back: Vec<i32> = cross_decode(&fixed);
+    assert_eq!(back, vec);
+    assert_eq!(fixed.to_vec().unwrap(), vec.to_vec().unwrap());
+}
+
+#[test]
+fn string_cross_decodes() {
+    let text = "héllo".to_string();
+    let fixed: heapless::String<16> = cross_decode(&text);
+    assert_eq!(fixed, text.as_str());
+
+    let back: String = cross_decode(&fixed);
+    assert_eq!(back, text);
+}
+
+#[test]
+fn index_map_cross_decodes_with_pairs() {
+    let pairs = vec![(3u8, "three".to_string()), (1, "one".to_string())];
+    let map: FnvIndexMap<u8, String, 4> = cross_decode(&pairs);
+    assert_eq!(map.get(&3).map(String::as_str), Some("three"));
+    assert_eq!(map.get(&1).map(String::as_str), Some("one"));
+
+    // The map keeps its insertion order, so it encodes the same way.
+    let back: Vec<(u8, String)> = cross_decode(&map);
+    assert_eq!(back, pairs);
+}
@@ -0,0 +1,252 @@
+//! The derived code only refers to `buffin` by absolute path, so it doesn't need anything in
+//! scope, and isn't confused by local items with the same names. Its locals don't clash with
+//! fields either, nor its lifetime with those of the type.
+
+use buffin_derive::{FromBytes, ToBytes};
+
+#[allow(dead_code)]
+mod nom {}
+#[allow(dead_code)]
+mod buffin {}
+#[allow(dead_code)]
+struct Buffin;
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[tag("pt")]
+struct Point<T> {
+    x: T,
+    y: T,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum Shape {
+    Dot(Point<u8>),
+    Line { from: Point<u8>, to: Point<u8> },
+    Empty,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum Event {
+    Moved(Point<i16>),
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+}
+
+mod codec {
+    pub use ::buffin as inner;
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(crate = "crate::codec::inner")]
+struct Reexported {
+    value: u16,
+    shape: Shape,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[tag("io")]
+struct Io {
+    buffer: Vec<u8>,
+    limits: u8,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum Config {
+    Set(u8),
+    Limits { limits: u16, buffer: String },
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum Request {
+    Write {
+        buffer: Vec<u8>,
+    },
+    Send {
+        payload: Vec<u8>,
+        rest: u32,
+    },
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Borrowed<'de, 'de1> {
+    name: &'de str,
+    raw: &'de1 [u8],
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum View<'de> {
+    Text(&'de str),
+    Empty,
+}
+
+fn tag() -> u8 {
+    7
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum Command {
+    Set {
+        tag: u8,
+        input: u16,
+    },
+    Reset,
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u16")]
+enum Key {
+    Input {
+        value: u8,
+        #[buffin(skip, default = "tag")]
+        fallback: u8,
+    },
+    Other(u8),
+}
+
+fn round_trip<T>(value: T)
+where
+    T: ::buffin::ToBytes + ::buffin::FromBytesOwned + PartialEq + std::fmt::Debug,
+{
+    let bytes = ::buffin::ToBytes::to_vec(&value).unwrap();
+    let (rest, decoded) = <T as ::buffin::FromBytes>::from_bytes(&bytes).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(decoded, value);
+}
+
+#[test]
+fn nothing_needs_to_be_in_scope() {
+    round_trip(Point { x: 1u32, y: 2 });
+    round_trip(Shape::Line {
+        from: Point { x: 1, y: 2 },
+        to: Point { x: 3, y: 4 },
+    });
+    round_trip(Shape::Empty);
+    round_trip(Event::Moved(Point { x: -1, y: 1 }));
+    round_trip(Event::Unknown(9, vec![1, 2]));
+}
+
+#[test]
+fn crate_path() {
+    round_trip(Reexported {
+        value: 7,
+        shape: Shape::Dot(Point { x: 5, y: 6 }),
+    });
+}
+
+#[test]
+fn lifetimes_named_de() {
+    let borrowed = Borrowed {
+        name: "x",
+        raw: &[1, 2],
+    };
+    let bytes = ::buffin::ToBytes::to_vec(&borrowed).unwrap();
+    let (rest, decoded) = <Borrowed as ::buffin::FromBytes>::from_bytes(&bytes).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(decoded, borrowed);
+
+    let view = View::Text("y");
+    let bytes = ::buffin::ToBytes::to_vec(&view).unwrap();
+    let (rest, decoded) = <View as ::buffin::FromBytes>::from_bytes(&bytes).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(decoded, view);
+}
+
+#[test]
+fn fields_named_like_locals() {
+    let io = Io {
+        buffer: vec![1, 2],
+        limits: 3,
+    };
+    let bytes = ::buffin::ToBytes::to_vec(&io).unwrap();
+    assert_eq!(bytes, b"io\x02\x00\x00\x00\x01\x02\x03");
+    round_trip(io);
+    round_trip(Config::Limits {
+        limits: 4,
+        buffer: "x".to_string(),
+    });
+
+    let bytes = ::buffin::ToBytes::to_vec(&Request::Write { buffer: vec![3] }).unwrap();
+    assert_eq!(bytes, b"\x00\x05\x00\x00\x00\x01\x00\x00\x00\x03");
+    round_trip(Request::Write { buffer: vec![3] });
+    round_trip(Request::Send {
+        payload: vec![5, 6],
+        rest: 7,
+    });
+
+    round_trip(Command::Set { tag: 1, input: 2 });
+    round_trip(Command::Reset);
+    round_trip(Command::Unknown(5, vec![1, 2]));
+    round_trip(Key::Input {
+        value: 1,
+        fallback: 7,
+    });
+    round_trip(Key::Other(3));
+}
+
+/// Items named like the prelude and nom ones the derived code uses.
+#[allow(dead_code, non_camel_case_types)]
+mod shadowed {
+    use super::round_trip;
+    use buffin_derive::{FromBytes, ToBytes};
+
+    enum Option {
+        X,
+    }
+    struct Some;
+    struct None;
+    struct Ok;
+    struct Err;
+
+    fn map() -> u8 {
+        3
+    }
+
+    #[derive(Debug, PartialEq, ToBytes, FromBytes)]
+    struct Plain {
+        x: u8,
+    }
+
+    #[derive(Debug, PartialEq, ToBytes, FromBytes)]
+    enum Named {
+        A(u8),
+        B {
+            #[buffin(skip, default = "map")]
+            y: u8,
+            z: u16,
+        },
+        C,
+    }
+
+    #[derive(Debug, PartialEq, ToBytes, FromBytes)]
+    #[repr(u8)]
+    enum Mode {
+        Idle = 0,
+        Run = 1,
+    }
+
+    #[derive(Debug, PartialEq, ToBytes, FromBytes)]
+    #[buffin(tag_type = "u8", length_delimited)]
+    enum Frame {
+        Data(u8),
+        #[buffin(other)]
+        Unknown(u8, Vec<u8>),
+    }
+
+    #[test]
+    fn prelude_and_nom_names_can_be_shadowed() {
+        round_trip(Plain { x: 1 });
+        round_trip(Named::A(2));
+        round_trip(Named::B { y: 3, z: 4 });
+        round_trip(Named::C);
+        round_trip(Mode::Run);
+        round_trip(Frame::Data(5));
+        round_trip(Frame::Unknown(9, vec![1]));
+    }
+}
@@ -0,0 +1,198 @@
+//! Decoding a partial item reports how many more bytes are needed.
+
+use buffin::{Buffin, PopFailure, ToBytes};
+use buffin_derive::{FromBytes, ToBytes};
+use std::{fmt::Debug, num::NonZeroUsize};
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Greeting {
+    id: u8,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum Command {
+    #[tag("p")]
+    Ping(u32),
+    #[tag("s")]
+    Say { text: String },
+}
+
+/// Pops a `T` from the first `len` bytes of `value`'s encoding, expecting it to be incomplete.
+fn needed<T: ToBytes + buffin::FromBytesOwned + Debug>(value: &T, len: usize) -> Option<usize> {
+    let mut bytes = value.to_vec().unwrap();
+    bytes.truncate(len);
+
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    match buffer.pop::<T>() {
+        Err(PopFailure::Incomplete { needed }) => needed.map(NonZeroUsize::get),
+        other => panic!("expected Incomplete, got {other:?}"),
+    }
+}
+
+#[test]
+fn number() {
+    assert_eq!(needed(&0xdead_beefu32, 0), Some(4));
+    assert_eq!(needed(&0xdead_beefu32, 1), Some(3));
+}
+
+#[test]
+fn string() {
+    let value = "hello".to_string();
+    assert_eq!(needed(&value, 2), Some(2));
+    assert_eq!(needed(&value, 4), Some(5));
+    assert_eq!(needed(&value, 6), Some(3));
+}
+
+#[test]
+fn vec() {
+    let bytes = vec![1u8, 2, 3, 4];
+    assert_eq!(needed(&bytes, 5), Some(3));
+
+    let numbers = vec![1u16, 2, 3];
+    assert_eq!(needed(&numbers, 7), Some(3));
+
+    let strings = vec!["ab".to_string(), "cd".to_string()];
+    assert_eq!(needed(&strings, 11), Some(3));
+    assert_eq!(needed(&strings, 15), Some(1));
+}
+
+#[test]
+fn derived_struct() {
+    let value = Greeting {
+        id: 1,
+        name: "bob".to_string(),
+    };
+    assert_eq!(needed(&value, 1), Some(4));
+    assert_eq!(needed(&value, 5), Some(3));
+}
+
+#[test]
+fn derived_enum() {
+    assert_eq!(needed(&Command::Ping(7), 0), Some(1));
+    assert_eq!(needed(&Command::Ping(7), 3), Some(2));
+
+    let say = Command::Say {
+        text: "hi".to_string(),
+    };
+    assert_eq!(needed(&say, 5), Some(2));
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum Message {
+    #[tag("j")]
+    Join {
+        channel: String,
+    },
+    #[tag("l")]
+    Leave {
+        channel: String,
+    },
+    Say {
+        channel: String,
+        message: String,
+    },
+    Quit,
+    Kick(u8, String),
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[tag("hello")]
+struct Tagged {
+    value: u32,
+    message: Option<Message>,
+}
+
+/// Pops `value` from every proper prefix of its encoding, expecting each to be incomplete, and
+/// then from the whole encoding.
+fn assert_every_prefix_incomplete<T: ToBytes + buffin::FromBytesOwned + PartialEq + Debug>(
+    value: &T,
+) {
+    let bytes = value.to_vec().unwrap();
+
+    for len in 0..bytes.len() {
+        let mut prefix = bytes[..len].to_vec();
+        let mut buffer = Buffin::new_filled(&mut prefix);
+        match buffer.pop::<T>() {
+            Err(PopFailure::Incomplete { .. }) => {}
+            other => panic!(
+                "expected Incomplete with {len} of {} bytes, got {other:?}",
+                bytes.len()
+            ),
+        }
+    }
+
+    let mut whole = bytes.clone();
+    let mut buffer = Buffin::new_filled(&mut whole);
+    assert_eq!(&buffer.pop::<T>().unwrap(), value);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn every_prefix_of_an_enum() {
+    assert_every_prefix_incomplete(&Message::Join {
+        channel: "general".to_string(),
+    });
+    assert_every_prefix_incomplete(&Message::Leave {
+        channel: "general".to_string(),
+    });
+    assert_every_prefix_incomplete(&Message::Say {
+        channel: "general".to_string(),
+        message: "hi".to_string(),
+    });
+    assert_every_prefix_incomplete(&Message::Quit);
+    assert_every_prefix_incomplete(&Message::Kick(3, "spam".to_string()));
+    assert_every_prefix_incomplete(&Command::Ping(7));
+}
+
+#[test]
+fn every_prefix_of_a_tagged_struct() {
+    assert_every_prefix_incomplete(&Tagged {
+        value: 1,
+        message: Some(Message::Say {
+            channel: "general".to_string(),
+            message: "hi".to_string(),
+        }),
+    });
+    assert_every_prefix_incomplete(&Tagged {
+        value: 1,
+        message: None,
+    });
+}
+
+#[test]
+fn every_prefix_of_nested_collections() {
+    assert_every_prefix_incomplete(&vec![
+        Command::Ping(1),
+        Command::Say {
+            text: "x".to_string(),
+        },
+    ]);
+    assert_every_prefix_incomplete(&(
+        Greeting {
+            id: 2,
+            name: "eve".to_string(),
+        },
+        Some(3u16),
+    ));
+}
+
+#[test]
+fn partial_multi_byte_tag() {
+    let mut bytes = *b"Sa";
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    match buffer.pop::<Message>() {
+        Err(PopFailure::Incomplete { needed }) => assert_eq!(needed, NonZeroUsize::new(1)),
+        other => panic!("expected Incomplete, got {other:?}"),
+    }
+}
+
+#[test]
+fn unknown_tag_is_invalid() {
+    let mut bytes = *b"x";
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    assert!(matches!(
+        buffer.pop::<Message>(),
+        Err(PopFailure::Invalid(_))
+    ));
+}
@@ -0,0 +1,168 @@
+//! Strings and collections can use a smaller length prefix than the default u32.
+
+mod common;
+
+use buffin::{
+    Buffin, ErrorKind, FromBytes, PopFailure, ToBytes,
+    len_prefix::{LenPrefix, Prefixed, Varint},
+};
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Beacon {
+    #[buffin(len = "u8")]
+    name: String,
+    #[buffin(len = "u16")]
+    readings: Vec<i16>,
+    #[buffin(len = "varint")]
+    payload: Vec<u8>,
+    #[buffin(len = "u32")]
+    note: String,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Label<'a> {
+    #[buffin(len = "u8")]
+    text: &'a str,
+    #[buffin(len = "u8")]
+    raw: &'a [u8],
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum Radio {
+    Ping(#[buffin(len = "u8")] heapless::String<8>),
+    Data {
+        #[buffin(len = "varint")]
+        bytes: heapless::Vec<u8, 16>,
+    },
+}
+
+#[test]
+fn fields_use_their_prefix() {
+    let beacon = Beacon {
+        name: "attic".to_string(),
+        readings: vec![-1, 2],
+        payload: vec![0xaa; 3],
+        note: String::new(),
+    };
+    let bytes = beacon.to_vec().unwrap();
+
+    let readings = (-1i16, 2i16).to_vec().unwrap();
+    let expected = [
+        &b"\x05attic\x02\x00"[..],
+        &readings,
+        b"\x03\xaa\xaa\xaa\x00\x00\x00\x00",
+    ]
+    .concat();
+    assert_eq!(bytes, expected);
+    assert_eq!(beacon.encoded_len(), bytes.len());
+    assert_eq!(common::decode::<Beacon>(&bytes), beacon);
+}
+
+#[test]
+fn borrowed_fields() {
+    let label = Label {
+        text: "hi",
+        raw: &[1, 2, 3],
+    };
+    let bytes = label.to_vec().unwrap();
+    assert_eq!(bytes, b"\x02hi\x03\x01\x02\x03");
+
+    let (rest, decoded) = Label::from_bytes(&bytes).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(decoded, label);
+}
+
+#[test]
+fn max_size_uses_the_prefix() {
+    assert_eq!(Radio::MAX_SIZE, Some("Data".len() + Varint::MAX_SIZE + 16));
+    assert_eq!(<Prefixed<u8, heapless::String<8>>>::MAX_SIZE, Some(9));
+    assert_eq!(Beacon::MAX_SIZE, None);
+}
+
+#[test]
+fn heapless_variants() {
+    let ping = Radio::Ping(heapless::String::try_from("hello").unwrap());
+    let bytes = ping.to_vec().unwrap();
+    assert_eq!(bytes, b"Ping\x05hello");
+    assert_eq!(common::decode::<Radio>(&bytes), ping);
+
+    let data = Radio::Data {
+        bytes: heapless::Vec::from_slice(&[7; 10]).unwrap(),
+    };
+    assert_eq!(common::decode::<Radio>(&data.to_vec().unwrap()), data);
+}
+
+#[test]
+fn encoding_fails_when_the_length_does_not_fit() {
+    let label = Label {
+        text: &"x".repeat(256),
+        raw: &[],
+    };
+    let mut raw = [0; 512];
+    let mut buffer = Buffin::new(&mut raw);
+
+    let err = buffer.add(&label).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        ErrorKind::LengthLimitExceeded {
+            len: 256,
+            limit: 255
+        }
+    );
+    assert_eq!(err.path().to_string(), "Label.text");
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn varint_lengths() {
+    for (len, prefix) in [
+        (0, &[0x00][..]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (300, &[0xac, 0x02]),
+    ] {
+        let value = Prefixed::<Varint, Vec<u8>>::new(vec![0; len]);
+        let bytes = value.to_vec().unwrap();
+        assert_eq!(&bytes[..prefix.len()], prefix, "length {len}");
+        assert_eq!(value.encoded_len(), bytes.len());
+        assert_eq!(common::decode::<Prefixed<Varint, Vec<u8>>>(&bytes), value);
+    }
+}
+
+#[test]
+fn varint_waits_for_the_rest_of_the_length() {
+    let mut raw = [0x80, 0x80];
+    let mut buffer = Buffin::new_filled(&mut raw);
+    assert!(matches!(
+        buffer.pop::<Prefixed<Varint, Vec<u8>>>(),
+        Err(PopFailure::Incomplete { .. })
+    ));
+}
+
+#[test]
+fn varint_too_large_for_a_length() {
+    let mut raw = [0xff; 12];
+    let mut buffer = Buffin::new_filled(&mut raw);
+    let Err(PopFailure::Invalid(err)) = buffer.pop::<Prefixed<Varint, Vec<u8>>>() else {
+        panic!("expected an invalid length");
+    };
+    assert_eq!(
+        err.kind(),
+        ErrorKind::Parse(nom::error::ErrorKind::TooLarge)
+    );
+    assert_eq!(err.offset(), Some(0));
+}
+
+#[test]
+fn wrapper_fails_cleanly_too() {
+    let value = Prefixed::<u16, &[u8]>::new(&[0; 70_000]);
+    let err = value.to_vec().unwrap_err();
+    assert_eq!(
+        err.kind(),
+        ErrorKind::LengthLimitExceeded {
+            len: 70_000,
+            limit: u16::MAX as usize
+        }
+    );
+}
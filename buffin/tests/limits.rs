@@ -0,0 +1,199 @@
+//! Decoding checks lengths and nesting against the limits, before waiting for or allocating
+//! anything.
+
+use buffin::{Buffin, ErrorKind, Limits, PopFailure, ToBytes};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Passes allocations on to the system allocator, remembering the largest one.
+struct LargestAllocation;
+
+static LARGEST_ALLOCATION: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for LargestAllocation {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: LargestAllocation = LargestAllocation;
+
+/// Pops a `T` from `bytes` within `limits`, expecting it to be invalid, and returns the kind and
+/// offset of the error.
+fn pop_invalid<T: buffin::FromBytesOwned>(bytes: &[u8], limits: Limits) -> (ErrorKind, usize) {
+    let mut raw = bytes.to_vec();
+    let mut buffer = Buffin::new_filled(&mut raw);
+    let Err(PopFailure::Invalid(err)) = buffer.pop_with::<T>(limits) else {
+        panic!("expected the value to be invalid");
+    };
+    // A failed pop leaves the bytes where they were.
+    assert_eq!(buffer.len(), bytes.len());
+    (err.kind(), err.offset().expect("pop locates the error"))
+}
+
+#[test]
+fn defaults_are_finite() {
+    let limits = Limits::default();
+    assert_eq!(limits, Limits::DEFAULT);
+    assert_eq!(limits.max_collection_len(), 1 << 20);
+    assert_eq!(limits.max_string_len(), 1 << 20);
+    assert_eq!(limits.max_depth(), 128);
+}
+
+#[test]
+fn collection_len() {
+    let limits = Limits::default().with_max_collection_len(3);
+    let bytes = (1u8, vec![1u16, 2, 3, 4]).to_vec().unwrap();
+
+    assert_eq!(
+        pop_invalid::<(u8, Vec<u16>)>(&bytes, limits),
+        (ErrorKind::LengthLimitExceeded { len: 4, limit: 3 }, 1)
+    );
+
+    let bytes = vec![1u16, 2, 3].to_vec().unwrap();
+    let mut raw = bytes.clone();
+    let mut buffer = Buffin::new_filled(&mut raw);
+    assert_eq!(buffer.pop_with::<Vec<u16>>(limits).unwrap(), [1, 2, 3]);
+}
+
+#[test]
+fn byte_slices_count_as_collections() {
+    let limits = Limits::default().with_max_collection_len(2);
+    let mut raw = ToBytes::to_vec(&&[7u8; 3][..]).unwrap();
+    let buffer = Buffin::new_filled(&mut raw);
+
+    let Err(PopFailure::Invalid(err)) = buffer.peek_with::<&[u8]>(limits) else {
+        panic!("expected the slice to be too long");
+    };
+    assert_eq!(
+        err.kind(),
+        ErrorKind::LengthLimitExceeded { len: 3, limit: 2 }
+    );
+    assert_eq!(err.offset(), Some(0));
+}
+
+#[test]
+fn string_len() {
+    let limits = Limits::default().with_max_string_len(4);
+    let bytes = (7u16, "hello".to_string()).to_vec().unwrap();
+
+    assert_eq!(
+        pop_invalid::<(u16, String)>(&bytes, limits),
+        (ErrorKind::LengthLimitExceeded { len: 5, limit: 4 }, 2)
+    );
+
+    // The string limit doesn't apply to collections, nor the other way round.
+    let limits = limits.with_max_collection_len(0);
+    let mut raw = "four".to_vec().unwrap();
+    let mut buffer = Buffin::new_filled(&mut raw);
+    assert_eq!(buffer.pop_with::<String>(limits).unwrap(), "four");
+}
+
+#[test]
+fn lengths_are_checked_before_waiting_for_the_rest() {
+    // Claims a billion items, but only a few bytes have arrived.
+    let mut raw = [0x00, 0xca, 0x9a, 0x3b, 1, 2];
+    let mut buffer = Buffin::new_filled(&mut raw);
+
+    let Err(PopFailure::Invalid(err)) = buffer.pop::<Vec<u8>>() else {
+        panic!("expected the default limit to reject the length");
+    };
+    assert_eq!(
+        err.kind(),
+        ErrorKind::LengthLimitExceeded {
+            len: 1_000_000_000,
+            limit: 1 << 20
+        }
+    );
+}
+
+#[test]
+fn depth() {
+    let nested = Some(Some(Some(1u8)));
+    let bytes = nested.to_vec().unwrap();
+
+    let mut raw = bytes.clone();
+    let mut buffer = Buffin::new_filled(&mut raw);
+    let limits = Limits::default().with_max_depth(3);
+    assert_eq!(
+        buffer
+            .pop_with::<Option<Option<Option<u8>>>>(limits)
+            .unwrap(),
+        nested
+    );
+
+    let limits = Limits::default().with_max_depth(2);
+    assert_eq!(
+        pop_invalid::<Option<Option<Option<u8>>>>(&bytes, limits),
+        (ErrorKind::DepthLimitExceeded { limit: 2 }, 2)
+    );
+}
+
+#[test]
+fn nested_collections_use_up_depth() {
+    let bytes = vec![vec![vec![1u8]]].to_vec().unwrap();
+    let limits = Limits::default().with_max_depth(2);
+
+    let (kind, _) = pop_invalid::<Vec<Vec<Vec<u8>>>>(&bytes, limits);
+    assert_eq!(kind, ErrorKind::DepthLimitExceeded { limit: 2 });
+}
+
+#[test]
+fn peek_with_overrides_the_limits() {
+    let mut raw = "hello".to_vec().unwrap();
+    let buffer = Buffin::new_filled(&mut raw);
+
+    let (text, len) = buffer.peek::<&str>().unwrap();
+    assert_eq!((text, len), ("hello", 9));
+
+    let Err(PopFailure::Invalid(err)) =
+        buffer.peek_with::<&str>(Limits::default().with_max_string_len(2))
+    else {
+        panic!("expected the string to be too long");
+    };
+    assert_eq!(
+        err.kind(),
+        ErrorKind::LengthLimitExceeded { len: 5, limit: 2 }
+    );
+}
+
+#[test]
+fn pop_with_overrides_the_limits() {
+    let bytes = vec![0u8; 16].to_vec().unwrap();
+
+    let limits = Limits::default().with_max_collection_len(15);
+    let (kind, offset) = pop_invalid::<Vec<u8>>(&bytes, limits);
+    assert_eq!(kind, ErrorKind::LengthLimitExceeded { len: 16, limit: 15 });
+    assert_eq!(offset, 0);
+
+    // Looser than the default.
+    let big = vec![0u8; (1 << 20) + 1];
+    let mut raw = big.to_vec().unwrap();
+    let mut buffer = Buffin::new_filled(&mut raw);
+    let limits = Limits::default().with_max_collection_len(usize::MAX);
+    assert_eq!(buffer.pop_with::<Vec<u8>>(limits).unwrap(), big);
+}
+
+#[test]
+fn large_items_are_not_preallocated() {
+    // A length within the default limit, and plenty of bytes, but far too few for 2^20 items of a
+    // KiB each. Trusting the length would reserve a GiB up front.
+    let mut raw = (1u32 << 20, [0u8; 256 * 1024]).to_vec().unwrap();
+    let mut buffer = Buffin::new_filled(&mut raw);
+
+    LARGEST_ALLOCATION.store(0, Ordering::Relaxed);
+    assert!(matches!(
+        buffer.pop::<Vec<[u8; 1024]>>(),
+        Err(PopFailure::Incomplete { .. })
+    ));
+    // Other tests may allocate a few MiB at the same time, but nothing like this would.
+    assert!(LARGEST_ALLOCATION.load(Ordering::Relaxed) < 16 << 20);
+}
@@ -0,0 +1,129 @@
+//! Enums with `#[buffin(tag_type = "...")]` are tagged with integers.
+
+use buffin::{Buffin, ErrorKind, PopFailure, ToBytes};
+use buffin_derive::{FromBytes, ToBytes};
+use std::fmt::Debug;
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8")]
+#[repr(u8)]
+enum Message {
+    Join {
+        channel: String,
+    },
+    Leave {
+        channel: String,
+    },
+    #[tag(10)]
+    Say(String, String),
+    Quit,
+    Ping = 40,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u16")]
+enum Wide {
+    A = 300,
+    B,
+}
+
+fn round_trip<T: ToBytes + buffin::FromBytesOwned + PartialEq + Debug>(value: T, expected: &[u8]) {
+    let mut bytes = value.to_vec().unwrap();
+    assert_eq!(bytes, expected);
+    assert_eq!(value.encoded_len(), expected.len());
+
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    assert_eq!(buffer.pop::<T>().unwrap(), value);
+}
+
+#[test]
+fn tags_count_up_in_declaration_order() {
+    round_trip(
+        Message::Join {
+            channel: "a".to_string(),
+        },
+        &[0, 1, 0, 0, 0, b'a'],
+    );
+    round_trip(
+        Message::Leave {
+            channel: "a".to_string(),
+        },
+        &[1, 1, 0, 0, 0, b'a'],
+    );
+}
+
+#[test]
+fn explicit_tags_and_discriminants() {
+    round_trip(
+        Message::Say("a".to_string(), "b".to_string()),
+        &[10, 1, 0, 0, 0, b'a', 1, 0, 0, 0, b'b'],
+    );
+    round_trip(Message::Quit, &[11]);
+    round_trip(Message::Ping, &[40]);
+}
+
+#[test]
+fn wider_tags() {
+    round_trip(Wide::A, &[0x2c, 0x01]);
+    round_trip(Wide::B, &[0x2d, 0x01]);
+    assert_eq!(Wide::MAX_SIZE, Some(2));
+}
+
+#[test]
+fn unknown_tag() {
+    let mut bytes = [7];
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    match buffer.pop::<Message>() {
+        Err(PopFailure::Invalid(err)) => assert!(matches!(err.kind(), ErrorKind::BadTag { .. })),
+        other => panic!("expected Invalid, got {other:?}"),
+    }
+}
+
+#[test]
+fn partial_tag() {
+    let mut bytes = [0x2c];
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    assert!(matches!(
+        buffer.pop::<Wide>(),
+        Err(PopFailure::Incomplete { .. })
+    ));
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[repr(u8)]
+enum Mode {
+    Idle = 0,
+    Run = 1,
+    Fault = 0x7F,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[repr(u16)]
+enum Register {
+    Status = 0x100,
+    Control,
+}
+
+#[test]
+fn repr_enums_are_encoded_as_their_discriminant() {
+    round_trip(Mode::Idle, &[0]);
+    round_trip(Mode::Run, &[1]);
+    round_trip(Mode::Fault, &[0x7f]);
+    assert_eq!(Mode::MAX_SIZE, Some(1));
+
+    round_trip(Register::Status, &[0x00, 0x01]);
+    round_trip(Register::Control, &[0x01, 0x01]);
+}
+
+#[test]
+fn repr_enums_reject_unknown_values() {
+    let mut bytes = [2];
+    let mut buffer = Buffin::new_filled(&mut bytes);
+    match buffer.pop::<Mode>() {
+        Err(PopFailure::Invalid(err)) => {
+            assert_eq!(err.kind(), ErrorKind::InvalidValue { value: 2 });
+            assert_eq!(err.offset(), Some(0));
+        }
+        other => panic!("expected Invalid, got {other:?}"),
+    }
+}
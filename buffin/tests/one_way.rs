@@ -0,0 +1,72 @@
+//! Types can derive just one direction, and still agree with the other on the wire format.
+
+use buffin::{Buffin, FromBytes, ToBytes};
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes)]
+#[tag("ack")]
+struct AckOut;
+
+#[derive(Debug, PartialEq, FromBytes)]
+#[tag("ack")]
+struct AckIn;
+
+#[derive(ToBytes)]
+#[tag("cmd")]
+enum CommandOut {
+    #[tag("p")]
+    Ping(u16),
+    Stop,
+}
+
+#[derive(Debug, PartialEq, FromBytes)]
+#[tag("cmd")]
+enum CommandIn {
+    #[tag("p")]
+    Ping(u16),
+    Stop,
+}
+
+#[derive(ToBytes)]
+#[buffin(tag_type = "u16")]
+enum LevelOut {
+    Low = 3,
+    High,
+}
+
+#[derive(Debug, PartialEq, FromBytes)]
+#[buffin(tag_type = "u16")]
+enum LevelIn {
+    Low = 3,
+    High,
+}
+
+fn convert<In: for<'de> FromBytes<'de>>(value: &impl ToBytes) -> In {
+    let mut raw = [0; 64];
+    let mut buffer = Buffin::new(&mut raw);
+    buffer.add(value).unwrap();
+    let decoded = buffer.pop::<In>().unwrap();
+    assert!(buffer.is_empty());
+    decoded
+}
+
+#[test]
+fn unit_struct() {
+    assert_eq!(convert::<AckIn>(&AckOut), AckIn);
+}
+
+#[test]
+fn enum_with_tags() {
+    assert_eq!(
+        convert::<CommandIn>(&CommandOut::Ping(7)),
+        CommandIn::Ping(7)
+    );
+    assert_eq!(convert::<CommandIn>(&CommandOut::Stop), CommandIn::Stop);
+}
+
+#[test]
+fn enum_with_integer_tags() {
+    assert_eq!(convert::<LevelIn>(&LevelOut::Low), LevelIn::Low);
+    assert_eq!(convert::<LevelIn>(&LevelOut::High), LevelIn::High);
+    assert_eq!(LevelOut::High.to_vec().unwrap(), 4u16.to_vec().unwrap());
+}
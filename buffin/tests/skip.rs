@@ -0,0 +1,127 @@
+//! Skipped fields aren't encoded, and get their default value when decoding.
+
+mod common;
+
+use buffin::ToBytes;
+use buffin_derive::{FromBytes, ToBytes};
+use std::time::Instant;
+
+fn never_seen() -> Option<Instant> {
+    None
+}
+
+fn unknown() -> u64 {
+    u64::MAX
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Sensor {
+    id: u8,
+    #[buffin(skip, default = "never_seen")]
+    last_seen: Option<Instant>,
+    name: String,
+    #[buffin(skip)]
+    cached_hash: u64,
+}
+
+/// What `Sensor` looks like on the wire.
+#[derive(ToBytes)]
+struct SensorWire {
+    id: u8,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Reading(u16, #[buffin(skip)] u64, i32);
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+enum Event {
+    Reading(Reading),
+    Renamed {
+        name: String,
+        #[buffin(skip, default = "unknown")]
+        hash: u64,
+    },
+    Reset(#[buffin(skip)] u8),
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum Framed {
+    Reading(#[buffin(skip)] u64, i32),
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+}
+
+#[test]
+fn skipped_fields_are_not_encoded() {
+    let sensor = Sensor {
+        id: 3,
+        last_seen: Some(Instant::now()),
+        name: "attic".to_string(),
+        cached_hash: 0xfeed,
+    };
+    let wire = SensorWire {
+        id: 3,
+        name: "attic".to_string(),
+    };
+    assert_eq!(sensor.to_vec().unwrap(), wire.to_vec().unwrap());
+    assert_eq!(sensor.encoded_len(), wire.encoded_len());
+
+    assert_eq!(
+        common::decode::<Sensor>(&sensor.to_vec().unwrap()),
+        Sensor {
+            id: 3,
+            last_seen: None,
+            name: "attic".to_string(),
+            cached_hash: 0,
+        }
+    );
+}
+
+#[test]
+fn max_size_ignores_skipped_fields() {
+    assert_eq!(Reading::MAX_SIZE, Some(6));
+    assert_eq!(Framed::MAX_SIZE, None);
+}
+
+#[test]
+fn tuple_struct() {
+    let bytes = Reading(1, 99, -2).to_vec().unwrap();
+    assert_eq!(bytes, (1u16, -2i32).to_vec().unwrap());
+    assert_eq!(common::decode::<Reading>(&bytes), Reading(1, 0, -2));
+}
+
+#[test]
+fn enum_variants() {
+    let bytes = Event::Renamed {
+        name: "porch".to_string(),
+        hash: 7,
+    }
+    .to_vec()
+    .unwrap();
+    assert_eq!(
+        common::decode::<Event>(&bytes),
+        Event::Renamed {
+            name: "porch".to_string(),
+            hash: u64::MAX,
+        }
+    );
+
+    let bytes = Event::Reset(5).to_vec().unwrap();
+    assert_eq!(bytes, b"Reset");
+    assert_eq!(common::decode::<Event>(&bytes), Event::Reset(0));
+
+    let bytes = Event::Reading(Reading(1, 2, 3)).to_vec().unwrap();
+    assert_eq!(
+        common::decode::<Event>(&bytes),
+        Event::Reading(Reading(1, 0, 3))
+    );
+}
+
+#[test]
+fn length_delimited_variants() {
+    let bytes = Framed::Reading(9, -4).to_vec().unwrap();
+    assert_eq!(bytes, (0u8, 4u32, -4i32).to_vec().unwrap());
+    assert_eq!(common::decode::<Framed>(&bytes), Framed::Reading(0, -4));
+}
@@ -0,0 +1,65 @@
+//! Types that don't accept every bit pattern reject the invalid ones, pointing at the bad value.
+
+use buffin::{Buffin, ErrorKind, FromBytesOwned, PopFailure};
+use std::num::{NonZeroI16, NonZeroU32};
+
+/// Pops `u8` padding and then a `T` from `bytes`, expecting the `T` to be invalid, and returns
+/// the error's kind and offset.
+fn pop_invalid<T: FromBytesOwned>(bytes: &[u8]) -> (ErrorKind, usize) {
+    let mut raw = bytes.to_vec();
+    let mut buffer = Buffin::new_filled(&mut raw);
+    let Err(PopFailure::Invalid(err)) = buffer.pop::<(u8, T)>() else {
+        panic!("expected the value to be invalid");
+    };
+    (err.kind(), err.offset().expect("pop locates the error"))
+}
+
+#[test]
+fn bool_other_than_0_or_1() {
+    assert_eq!(
+        pop_invalid::<bool>(&[0xaa, 2]),
+        (ErrorKind::InvalidValue { value: 2 }, 1)
+    );
+    assert_eq!(
+        pop_invalid::<bool>(&[0xaa, 0xff]),
+        (ErrorKind::InvalidValue { value: 0xff }, 1)
+    );
+}
+
+#[test]
+fn char_surrogate() {
+    assert_eq!(
+        pop_invalid::<char>(&[0xaa, 0x00, 0xd8, 0x00, 0x00]),
+        (ErrorKind::InvalidValue { value: 0xd800 }, 1)
+    );
+}
+
+#[test]
+fn char_out_of_range() {
+    assert_eq!(
+        pop_invalid::<char>(&[0xaa, 0x00, 0x00, 0x11, 0x00]),
+        (ErrorKind::InvalidValue { value: 0x11_0000 }, 1)
+    );
+}
+
+#[test]
+fn non_zero_zero() {
+    assert_eq!(
+        pop_invalid::<NonZeroU32>(&[0xaa, 0, 0, 0, 0]),
+        (ErrorKind::InvalidValue { value: 0 }, 1)
+    );
+    assert_eq!(
+        pop_invalid::<NonZeroI16>(&[0xaa, 0, 0]),
+        (ErrorKind::InvalidValue { value: 0 }, 1)
+    );
+}
+
+#[test]
+fn valid_values_still_decode() {
+    let mut raw = [1, 0, 0x41, 0, 0, 0, 7, 0, 0, 0];
+    let mut buffer = Buffin::new_filled(&mut raw);
+    assert_eq!(
+        buffer.pop::<(bool, bool, char, NonZeroU32)>().unwrap(),
+        (true, false, 'A', NonZeroU32::new(7).unwrap())
+    );
+}
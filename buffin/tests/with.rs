@@ -0,0 +1,97 @@
+//! Fields of foreign types can be encoded with custom functions instead of a newtype.
+
+use buffin::{Error, ErrorKind, FromBytes, ToBytes};
+use buffin_derive::{FromBytes, ToBytes};
+use nom::IResult;
+use std::time::Duration;
+
+/// Encodes a duration as whole milliseconds.
+mod millis {
+    use super::*;
+
+    pub fn to_bytes(value: &Duration, buffer: &mut [u8]) -> buffin::Result<usize> {
+        (value.as_millis() as u64).to_bytes(buffer)
+    }
+
+    pub fn encoded_len(_: &Duration) -> usize {
+        8
+    }
+
+    pub fn from_bytes(buffer: &[u8]) -> IResult<&[u8], Duration, Error> {
+        let (buffer, millis) = u64::from_bytes(buffer)?;
+        Ok((buffer, Duration::from_millis(millis)))
+    }
+}
+
+fn to_seconds(value: &Duration, buffer: &mut [u8]) -> buffin::Result<usize> {
+    (value.as_secs() as u32).to_bytes(buffer)
+}
+
+fn seconds_len(_: &Duration) -> usize {
+    4
+}
+
+fn from_seconds(buffer: &[u8]) -> IResult<&[u8], Duration, Error> {
+    let (buffer, seconds) = u32::from_bytes(buffer)?;
+    Ok((buffer, Duration::from_secs(seconds.into())))
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+struct Timeout {
+    id: u8,
+    #[buffin(with = "millis")]
+    after: Duration,
+    #[buffin(serialize_with = "to_seconds", encoded_len_with = "seconds_len")]
+    #[buffin(deserialize_with = "from_seconds")]
+    period: Duration,
+}
+
+#[derive(Debug, PartialEq, ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum Command {
+    Sleep(#[buffin(with = "millis")] Duration),
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+}
+
+#[test]
+fn struct_fields() {
+    let timeout = Timeout {
+        id: 1,
+        after: Duration::from_millis(1500),
+        period: Duration::from_secs(60),
+    };
+    let bytes = timeout.to_vec().unwrap();
+    assert_eq!(bytes, (1u8, 1500u64, 60u32).to_vec().unwrap());
+    assert_eq!(timeout.encoded_len(), bytes.len());
+
+    let (rest, decoded) = Timeout::from_bytes(&bytes).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(decoded, timeout);
+}
+
+#[test]
+fn max_size_is_unknown() {
+    assert_eq!(Timeout::MAX_SIZE, None);
+}
+
+#[test]
+fn length_delimited_variant() {
+    let bytes = Command::Sleep(Duration::from_millis(20)).to_vec().unwrap();
+    assert_eq!(bytes, (0u8, 8u32, 20u64).to_vec().unwrap());
+
+    let (rest, decoded) = Command::from_bytes(&bytes).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(decoded, Command::Sleep(Duration::from_millis(20)));
+}
+
+#[test]
+fn errors_name_the_field() {
+    // A `Sleep` with a two byte payload, which can't hold the milliseconds.
+    let bytes = (0u8, 2u32, 20u16).to_vec().unwrap();
+    let nom::Err::Failure(err) = Command::from_bytes(&bytes).unwrap_err() else {
+        panic!("expected a short payload to fail");
+    };
+    assert_eq!(err.kind(), ErrorKind::Parse(nom::error::ErrorKind::Eof));
+    assert_eq!(err.path().to_string(), "Command::Sleep.0");
+}
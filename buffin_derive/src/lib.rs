@@ -1,123 +1,71 @@
 use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
-use quote::{ToTokens, quote, quote_spanned};
-use syn::{DeriveInput, Expr, ExprLit, Lit, Meta, parse_macro_input, spanned::Spanned};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use std::{fmt, iter};
+use syn::{
+    Attribute, DeriveInput, Expr, ExprLit, GenericParam, Generics, LifetimeParam, Lit, LitStr,
+    Meta, Token, WherePredicate, parse_macro_input, parse_quote, punctuated::Punctuated,
+    spanned::Spanned,
+};
 
-#[proc_macro_derive(ToBytes, attributes(tag))]
+#[proc_macro_derive(ToBytes, attributes(tag, buffin))]
 pub fn derive_to_bytes(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
+    let name = input.ident.clone();
 
-    let mut type_tag_value = None;
-    for attr in &input.attrs {
-        if attr.path().is_ident("tag") {
-            match &attr.meta {
-                Meta::List(list) => {
-                    if let Ok(expr) = list.parse_args::<Expr>() {
-                        if let Expr::Lit(ExprLit {
-                            lit: Lit::Str(lit_str),
-                            ..
-                        }) = expr
-                        {
-                            type_tag_value = Some(lit_str.value());
-                        } else {
-                            println!("invalid tag");
-                        }
-                    } else {
-                        println!("invalid tag");
-                    }
-                }
-                Meta::NameValue(nv) => {
-                    if let Expr::Lit(ExprLit {
-                        lit: Lit::Str(lit_str),
-                        ..
-                    }) = &nv.value
-                    {
-                        type_tag_value = Some(lit_str.value());
-                    } else {
-                        println!("invalid tag");
-                    }
-                }
-                _ => {
-                    println!("invalid tag");
-                }
-            }
-        }
-    }
+    let Format {
+        attrs,
+        type_tag: type_tag_value,
+        tag_type,
+        variant_tags: tags,
+    } = match Format::parse(&input, Direction::ToBytes) {
+        Ok(format) => format,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let krate = attrs.krate();
+    let buffer = local("buffer");
+    let generics = with_bounds(&input.generics, &attrs, Direction::ToBytes);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let add_type_tag = match &type_tag_value {
         Some(tag) => quote! {
-            buffer.add_bytes(#tag.as_bytes())?;
+            #buffer.add_bytes(#tag.as_bytes())?;
         },
         None => quote! {},
     };
+    let type_tag_len = type_tag_value.as_ref().map_or(0, String::len);
 
     match input.data {
         syn::Data::Struct(data_struct) => {
-            let expanded = match data_struct.fields {
-                syn::Fields::Named(fields_named) => {
-                    let fields = fields_named.named.into_iter().map(|field| {
-                        let field_name =
-                            field
-                                .ident
-                                .clone()
-                                .expect(&format!("{}:{}", file!(), line!()));
-                        quote! {
-                            buffer.add(&self.#field_name)?;
-                        }
-                    });
-
-                    quote! {
-                        impl buffin::ToBytes for #name {
-                            fn to_bytes(&self, buffer: &mut [u8]) -> eyre::Result<usize> {
-                                let mut buffer = Buffin::new(buffer);
-                                #add_type_tag
-                                #( #fields )*
-                                Ok(buffer.len())
-                            }
-                        }
-                    }
-                }
-                syn::Fields::Unnamed(fields_unnamed) => {
-                    let field_bindings: Vec<_> = (0..fields_unnamed.unnamed.len())
-                        .map(|i| syn::Ident::new(&format!("f{i}"), fields_unnamed.span()))
-                        .collect();
-
-                    let field_adds = field_bindings.iter().map(|binding| {
-                        quote! {
-                            buffer.add(#binding)?;
-                        }
-                    });
-
-                    quote! {
-                        impl buffin::ToBytes for #name {
-                            fn to_bytes(&self, buffer: &mut [u8]) -> eyre::Result<usize> {
-                                let mut buffer = Buffin::new(buffer);
-                                #add_type_tag
+            let fields = fields_of(&data_struct.fields, &name.to_string());
+            let pattern = destructure(quote! { Self }, &data_struct.fields, &fields);
+            let encoded: Vec<_> = fields.iter().filter(|field| !field.attrs.skip).collect();
+            let values: Vec<_> = encoded
+                .iter()
+                .map(|field| field.encodable(&krate))
+                .collect();
+            let field_paths = encoded.iter().map(|field| &field.path);
+            let max_size = max_size_of(&krate, type_tag_len, encoded.iter().copied());
 
-                                let Self ( #( #field_bindings ),* ) = self;
+            let expanded = quote! {
+                impl #impl_generics #krate::ToBytes for #name #ty_generics #where_clause {
+                    const MAX_SIZE: ::core::option::Option<usize> = #max_size;
 
-                                #( #field_adds )*
-                                Ok(buffer.len())
-                            }
-                        }
-                    }
-                }
-                unit @ syn::Fields::Unit => {
-                    if type_tag_value.is_none() {
-                        quote_spanned! {
-                            unit.span() => compile_error!("unit structs must have a tag")
-                        };
+                    fn to_bytes(&self, #buffer: &mut [u8]) -> #krate::Result<usize> {
+                        let mut #buffer = #krate::Buffin::new(#buffer);
+                        #add_type_tag
+                        let #pattern = self;
+                        #(
+                            #buffer
+                                .add(#values)
+                                .map_err(|err| err.in_field(#field_paths))?;
+                        )*
+                        ::core::result::Result::Ok(#buffer.len())
                     }
 
-                    quote! {
-                        impl buffin::ToBytes for #name {
-                            fn to_bytes(&self, buffer: &mut [u8]) -> eyre::Result<usize> {
-                                let mut buffer = Buffin::new(buffer);
-                                #add_type_tag
-                                Ok(buffer.len())
-                            }
-                        }
+                    fn encoded_len(&self) -> usize {
+                        let #pattern = self;
+                        #type_tag_len #( + #krate::ToBytes::encoded_len(#values) )*
                     }
                 }
             };
@@ -125,240 +73,198 @@ pub fn derive_to_bytes(input: TokenStream) -> TokenStream {
             expanded.into()
         }
         syn::Data::Enum(data_enum) => {
-            let variant_branches = data_enum.variants.iter().map(|variant| {
+            let mut variant_branches = Vec::new();
+            let mut variant_lens = Vec::new();
+            let mut variant_max_sizes = Vec::new();
+
+            for (variant, (variant_tag, _)) in data_enum.variants.iter().zip(&tags) {
                 let variant_ident = &variant.ident;
-                let mut variant_name = variant_ident.to_string();
-
-                for attr in &variant.attrs {
-                    if attr.path().is_ident("tag") {
-                        match &attr.meta {
-                            Meta::List(list) => {
-                                // handles #[tag("something")]
-                                if let Ok(expr) = list.parse_args::<Expr>() {
-                                    if let Expr::Lit(ExprLit {
-                                        lit: Lit::Str(lit_str),
-                                        ..
-                                    }) = expr
-                                    {
-                                        variant_name = lit_str.value();
-                                    }
-                                }
-                            }
-                            Meta::NameValue(nv) => {
-                                // handles #[tag = "something"]
-                                if let Expr::Lit(ExprLit {
-                                    lit: Lit::Str(lit_str),
-                                    ..
-                                }) = &nv.value
-                                {
-                                    variant_name = lit_str.value();
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
+                let fields = fields_of(&variant.fields, &format!("{name}::{variant_ident}"));
+                let pattern =
+                    destructure(quote! { Self::#variant_ident }, &variant.fields, &fields);
+                let encoded: Vec<_> = fields.iter().filter(|field| !field.attrs.skip).collect();
+                let values: Vec<_> = encoded
+                    .iter()
+                    .map(|field| field.encodable(&krate))
+                    .collect();
+                let field_paths = encoded.iter().map(|field| &field.path);
+
+                let add_variant_tag = variant_tag.to_bytes(tag_type);
+                let mut prefix_len = variant_tag.len(tag_type);
+                let mut add_length = quote! {};
+                // The payload of the `other` variant already starts with its length.
+                if attrs.length_delimited && *variant_tag != Tag::Other {
+                    prefix_len += 4;
+                    add_length = quote! {
+                        #buffer.add(&((0 #( + #krate::ToBytes::encoded_len(#values) )*) as u32))?;
+                    };
                 }
+                variant_max_sizes.push(max_size_of(&krate, prefix_len, encoded.iter().copied()));
 
-                match &variant.fields {
-                    syn::Fields::Unit => {
-                        quote! {
-                            Self::#variant_ident => {
-                                buffer.add_bytes(#variant_name.as_bytes())?;
-                            }
-                        }
+                variant_branches.push(quote! {
+                    #pattern => {
+                        #add_variant_tag
+                        #add_length
+                        #(
+                            #buffer
+                                .add(#values)
+                                .map_err(|err| err.in_field(#field_paths))?;
+                        )*
                     }
-                    syn::Fields::Unnamed(fields_unnamed) => {
-                        let field_bindings: Vec<_> = (0..fields_unnamed.unnamed.len())
-                            .map(|i| syn::Ident::new(&format!("f{i}"), variant_ident.span()))
-                            .collect();
-
-                        let field_adds = field_bindings.iter().map(|binding| {
-                            quote! {
-                                buffer.add(#binding)?;
-                            }
-                        });
-
-                        quote! {
-                            Self::#variant_ident( #( #field_bindings ),* ) => {
-                                buffer.add_bytes(#variant_name.as_bytes())?;
-                                #( #field_adds )*
-                            }
-                        }
-                    }
-                    syn::Fields::Named(fields_named) => {
-                        let field_idents: Vec<_> = fields_named
-                            .named
-                            .iter()
-                            .map(|f| f.ident.as_ref().expect(&format!("{}:{}", file!(), line!())))
-                            .collect();
-
-                        let field_adds = field_idents.iter().map(|binding| {
-                            quote! {
-                                buffer.add(#binding)?;
-                            }
-                        });
-
-                        quote! {
-                            Self::#variant_ident { #( #field_idents ),* } => {
-                                buffer.add_bytes(#variant_name.as_bytes())?;
-                                #( #field_adds )*
-                            }
-                        }
-                    }
-                }
-            });
+                });
+                variant_lens.push(quote! {
+                    #pattern => #prefix_len #( + #krate::ToBytes::encoded_len(#values) )*,
+                });
+            }
+
+            let max_size = variant_max_sizes.into_iter().fold(
+                quote! { ::core::option::Option::Some(0) },
+                |acc, variant_max_size| {
+                    quote! { #krate::max_size::max(#acc, #variant_max_size) }
+                },
+            );
 
             let expanded = quote! {
-                impl buffin::ToBytes for #name {
-                    fn to_bytes(&self, buffer: &mut [u8]) -> eyre::Result<usize> {
-                        let mut buffer = Buffin::new(buffer);
+                impl #impl_generics #krate::ToBytes for #name #ty_generics #where_clause {
+                    const MAX_SIZE: ::core::option::Option<usize> =
+                        #krate::max_size::sum(::core::option::Option::Some(#type_tag_len), #max_size);
+
+                    fn to_bytes(&self, #buffer: &mut [u8]) -> #krate::Result<usize> {
+                        let mut #buffer = #krate::Buffin::new(#buffer);
                         #add_type_tag
                         match &self {
                             #( #variant_branches )*
                         }
-                        Ok(buffer.len())
+                        ::core::result::Result::Ok(#buffer.len())
+                    }
+
+                    fn encoded_len(&self) -> usize {
+                        #type_tag_len + match &self {
+                            #( #variant_lens )*
+                        }
                     }
                 }
             };
 
             expanded.into()
         }
-        other => {
-            let other = format!("{other:?}");
-            quote! {
-                compile_error!("`#[derive(FromBytes)]` cannot be used for {:?}", #other);
-            }
-            .into()
-        }
+        syn::Data::Union(_) => unreachable!("`Format::parse` rejects unions"),
     }
 }
 
-#[proc_macro_derive(FromBytes)]
+#[proc_macro_derive(FromBytes, attributes(tag, buffin))]
 pub fn derive_from_bytes(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
+    let name = input.ident.clone();
+    let de = de_lifetime(&input.generics);
 
-    let mut type_tag_value = None;
-    for attr in &input.attrs {
-        if attr.path().is_ident("tag") {
-            match &attr.meta {
-                Meta::List(list) => {
-                    if let Ok(expr) = list.parse_args::<Expr>() {
-                        if let Expr::Lit(ExprLit {
-                            lit: Lit::Str(lit_str),
-                            ..
-                        }) = expr
-                        {
-                            type_tag_value = Some(lit_str.value());
-                        } else {
-                            println!("invalid tag");
-                        }
-                    } else {
-                        println!("invalid tag");
-                    }
-                }
-                Meta::NameValue(nv) => {
-                    if let Expr::Lit(ExprLit {
-                        lit: Lit::Str(lit_str),
-                        ..
-                    }) = &nv.value
-                    {
-                        type_tag_value = Some(lit_str.value());
-                    } else {
-                        println!("invalid tag");
-                    }
-                }
-                _ => {
-                    println!("invalid tag");
-                }
-            }
-        }
-    }
+    let Format {
+        attrs,
+        type_tag: type_tag_value,
+        tag_type,
+        variant_tags: tags,
+    } = match Format::parse(&input, Direction::FromBytes(&de)) {
+        Ok(format) => format,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let krate = attrs.krate();
+    let (buffer, limits) = (local("buffer"), local("limits"));
+    let generics = with_de_lifetime(
+        with_bounds(&input.generics, &attrs, Direction::FromBytes(&de)),
+        &de,
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
 
     let get_type_tag = match &type_tag_value {
         Some(tag) => quote! {
-            let (buffer, _) = nom::bytes::streaming::tag(#tag.as_bytes())(buffer)?;
+            let (#buffer, _) = #krate::__private::nom::bytes::streaming::tag(#tag.as_bytes())(#buffer)?;
         },
         None => quote! {},
     };
 
+    // Types with fields decode them within the limits, one level deeper.
+    let limited_from_bytes = quote! {
+        fn from_bytes(#buffer: &#de [u8]) -> #krate::__private::nom::IResult<&#de [u8], Self, #krate::Error> {
+            <Self as #krate::FromBytes<#de>>::from_bytes_limited(#buffer, #krate::Limits::global())
+        }
+    };
+
     match input.data {
         syn::Data::Struct(data_struct) => {
-            let expanded = match data_struct.fields {
-                syn::Fields::Named(fields_named) => {
-                    let field_names = fields_named.named.iter().map(|field| {
-                        field
-                            .ident
-                            .clone()
-                            .expect(&format!("{}:{}", file!(), line!()))
-                    });
-
-                    let fields = fields_named.named.iter().map(|field| {
-                        let field_name =
-                            field
-                                .ident
-                                .as_ref()
-                                .expect(&format!("{}:{}", file!(), line!()));
-                        let ty = &field.ty;
-                        let ty_tokens = ty.to_token_stream();
-
-                        quote! {
-                            let (buffer, #field_name) = <#ty_tokens>::from_bytes(buffer)?;
-                        }
-                    });
-
-                    quote! {
-                        impl buffin::FromBytes for #name {
-                            fn from_bytes(buffer: &[u8]) -> nom::IResult<&[u8], Self> {
-                                #get_type_tag
-                                #( #fields )*
-                                Ok(( buffer, Self { #( #field_names ),* }))
-                            }
+            let fields = fields_of(&data_struct.fields, &name.to_string());
+            let decode = decode_fields(&krate, &de, &fields, false);
+            let construct = construct(quote! { Self }, &data_struct.fields, &fields);
+
+            let expanded = if fields.iter().any(|field| !field.attrs.skip) {
+                quote! {
+                    impl #impl_generics #krate::FromBytes<#de> for #name #ty_generics #where_clause {
+                        #limited_from_bytes
+
+                        fn from_bytes_limited(#buffer: &#de [u8], #limits: #krate::Limits) -> #krate::__private::nom::IResult<&#de [u8], Self, #krate::Error> {
+                            let #limits = #limits.nested(#buffer)?;
+                            #get_type_tag
+                            #( #decode )*
+                            ::core::result::Result::Ok((#buffer, #construct))
                         }
                     }
                 }
-                syn::Fields::Unnamed(fields_unnamed) => {
-                    // TODO: Require tag if there are no fields!
-
-                    let field_bindings: Vec<_> = (0..fields_unnamed.unnamed.len())
-                        .map(|i| syn::Ident::new(&format!("f{i}"), fields_unnamed.span()))
-                        .collect();
-
-                    let field_types: Vec<_> =
-                        fields_unnamed.unnamed.iter().map(|f| &f.ty).collect();
-
-                    let fields = field_types.into_iter().zip(&field_bindings).map(
-                        |(field, field_binding)| {
-                            let ty_tokens = field.to_token_stream();
-                            quote! {
-                                let (buffer, #field_binding) = <#ty_tokens>::from_bytes(buffer)?;
-                            }
-                        },
-                    );
-
-                    quote! {
-                        impl buffin::FromBytes for #name {
-                            fn from_bytes(buffer: &[u8]) -> nom::IResult<&[u8], Self> {
-                                #get_type_tag
-                                #( #fields )*
-                                Ok(( buffer, Self ( #( #field_bindings ),* )))
-                            }
+            } else {
+                quote! {
+                    impl #impl_generics #krate::FromBytes<#de> for #name #ty_generics #where_clause {
+                        fn from_bytes(#buffer: &#de [u8]) -> #krate::__private::nom::IResult<&#de [u8], Self, #krate::Error> {
+                            #get_type_tag
+                            #( #decode )*
+                            ::core::result::Result::Ok((#buffer, #construct))
                         }
                     }
                 }
-                unit @ syn::Fields::Unit => {
-                    if type_tag_value.is_none() {
-                        quote_spanned! {
-                            unit.span() => compile_error!("unit structs must have a tag")
-                        };
-                    }
+            };
 
-                    quote! {
-                        impl buffin::FromBytes for #name {
-                            fn from_bytes(buffer: &[u8]) -> nom::IResult<&[u8], Self> {
-                                #get_type_tag
-                                Ok(( buffer, Self ))
-                            }
+            expanded.into()
+        }
+        syn::Data::Enum(data_enum) => {
+            // Length-delimited payloads are limited too, even if they're empty.
+            let has_fields = attrs.length_delimited
+                || data_enum
+                    .variants
+                    .iter()
+                    .flat_map(|variant| &variant.fields)
+                    .any(|field| !FieldAttrs::of(field).skip);
+
+            let parse = match tag_type.int_type() {
+                Some(int_type) => numeric_enum_parser(
+                    &krate,
+                    &de,
+                    &name,
+                    &data_enum,
+                    &tags,
+                    &int_type,
+                    attrs.length_delimited,
+                ),
+                None => string_enum_parser(&krate, &de, &name, &data_enum, &tags),
+            };
+            let parse = quote! {
+                #get_type_tag
+                #parse
+            };
+
+            let expanded = if has_fields {
+                quote! {
+                    impl #impl_generics #krate::FromBytes<#de> for #name #ty_generics #where_clause {
+                        #limited_from_bytes
+
+                        fn from_bytes_limited(#buffer: &#de [u8], #limits: #krate::Limits) -> #krate::__private::nom::IResult<&#de [u8], Self, #krate::Error> {
+                            let #limits = #limits.nested(#buffer)?;
+                            #parse
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    impl #impl_generics #krate::FromBytes<#de> for #name #ty_generics #where_clause {
+                        fn from_bytes(#buffer: &#de [u8]) -> #krate::__private::nom::IResult<&#de [u8], Self, #krate::Error> {
+                            #parse
                         }
                     }
                 }
@@ -366,116 +272,1208 @@ pub fn derive_from_bytes(input: TokenStream) -> TokenStream {
 
             expanded.into()
         }
-        syn::Data::Enum(data_enum) => {
-            let mut variant_tokens = TokenStream2::new();
+        syn::Data::Union(_) => unreachable!("`Format::parse` rejects unions"),
+    }
+}
 
-            data_enum.variants.iter().for_each(|variant| {
-                let variant_ident = &variant.ident;
-                let mut variant_name = variant_ident.to_string();
-
-                for attr in &variant.attrs {
-                    if attr.path().is_ident("tag") {
-                        match &attr.meta {
-                            Meta::List(list) => {
-                                // handles #[tag("something")]
-                                if let Ok(expr) = list.parse_args::<Expr>() {
-                                    if let Expr::Lit(ExprLit {
-                                        lit: Lit::Str(lit_str),
-                                        ..
-                                    }) = expr
-                                    {
-                                        variant_name = lit_str.value();
-                                    }
-                                }
-                            }
-                            Meta::NameValue(nv) => {
-                                // handles #[tag = "something"]
-                                if let Expr::Lit(ExprLit {
-                                    lit: Lit::Str(lit_str),
-                                    ..
-                                }) = &nv.value
-                                {
-                                    variant_name = lit_str.value();
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
+/// Builds the body of `from_bytes` for an enum with string tags, trying the variants in order.
+fn string_enum_parser(
+    krate: &syn::Path,
+    de: &syn::Lifetime,
+    name: &syn::Ident,
+    data_enum: &syn::DataEnum,
+    tags: &[(Tag, Span)],
+) -> TokenStream2 {
+    let mut variant_tokens = TokenStream2::new();
+    let buffer = local("buffer");
+    let nom = quote! { #krate::__private::nom };
+
+    for (variant, (variant_tag, _)) in data_enum.variants.iter().zip(tags) {
+        let variant_ident = &variant.ident;
+        let Tag::Str(variant_name) = variant_tag else {
+            unreachable!("string enums have string tags");
+        };
+
+        let fields = fields_of(&variant.fields, &format!("{name}::{variant_ident}"));
+        let (encoded, skipped): (Vec<_>, Vec<_>) =
+            fields.iter().partition(|field| !field.attrs.skip);
+        let bindings = encoded.iter().map(|field| &field.binding);
+        let parsers = encoded.iter().map(|field| {
+            variant_field_parser(
+                krate,
+                field.decode(krate, de, quote! { #buffer }),
+                &field.path,
+            )
+        });
+        let defaults = decode_fields(krate, de, skipped, false);
+        let construct = construct(quote! { Self::#variant_ident }, &variant.fields, &fields);
+
+        variant_tokens.extend(if encoded.is_empty() {
+            quote! {
+                #nom::combinator::map(
+                    #nom::bytes::streaming::tag(#variant_name),
+                    |_| {
+                        #( #defaults )*
+                        #construct
+                    },
+                ),
+            }
+        } else {
+            quote! {
+                #nom::combinator::map(
+                    (#nom::bytes::streaming::tag(#variant_name), #( #parsers ),*),
+                    |(_, #( #bindings ),* )| {
+                        #( #defaults )*
+                        #construct
+                    },
+                ),
+            }
+        });
+    }
+
+    quote! {
+        #nom::Parser::parse(&mut #nom::branch::alt(( #variant_tokens )), #buffer)
+    }
+}
+
+/// Builds the body of `from_bytes` for an enum with integer tags, matching on the tag.
+///
+/// Length-delimited variants decode their fields from the payload, and an unknown tag is caught
+/// by the `#[buffin(other)]` variant, if there is one.
+fn numeric_enum_parser(
+    krate: &syn::Path,
+    de: &syn::Lifetime,
+    name: &syn::Ident,
+    data_enum: &syn::DataEnum,
+    tags: &[(Tag, Span)],
+    int_type: &syn::Ident,
+    length_delimited: bool,
+) -> TokenStream2 {
+    let (buffer, limits) = (local("buffer"), local("limits"));
+    let (payload, rest) = (local("payload"), local("rest"));
+    let (input, tag) = (local("input"), local("tag"));
+    let mut arms = Vec::new();
+    let mut other = None;
+
+    for (variant, (variant_tag, _)) in data_enum.variants.iter().zip(tags) {
+        let variant_ident = &variant.ident;
+
+        let fields = fields_of(&variant.fields, &format!("{name}::{variant_ident}"));
+        let construct = construct(quote! { Self::#variant_ident }, &variant.fields, &fields);
+
+        let value = match variant_tag {
+            Tag::Int(value) => value,
+            Tag::Other => {
+                // The tag goes in the first field, and the payload, length and all, in the second.
+                let (tag_binding, payload) = (&fields[0].binding, &fields[1]);
+                let (payload_binding, payload_path) = (&payload.binding, &payload.path);
+                let payload_ty = &payload.field.ty;
+                other = Some(quote! {
+                    let #tag_binding = #tag;
+                    let (#buffer, #payload_binding) =
+                        <#payload_ty as #krate::FromBytes<#de>>::from_bytes_limited(#buffer, #limits)
+                            .map_err(|err| err.map(|err| err.in_field(#payload_path)))?;
+                    ::core::result::Result::Ok((#buffer, #construct))
+                });
+                continue;
+            }
+            Tag::Str(_) => unreachable!("numeric enums have integer tags"),
+        };
+        let value = syn::LitInt::new(&format!("{value}{int_type}"), Span::call_site());
+
+        let decode = decode_fields(krate, de, &fields, length_delimited);
+
+        arms.push(if length_delimited {
+            quote! {
+                #value => {
+                    let (#buffer, #payload) =
+                        <&#de [u8] as #krate::FromBytes<#de>>::from_bytes_limited(#buffer, #limits)?;
+                    let #rest = #buffer.len();
+                    #( #decode )*
+                    // Anything left was added by a newer version of the variant, and is skipped.
+                    let _ = #payload;
+                    ::core::result::Result::Ok((#buffer, #construct))
+                }
+            }
+        } else {
+            quote! {
+                #value => {
+                    #( #decode )*
+                    ::core::result::Result::Ok((#buffer, #construct))
                 }
+            }
+        });
+    }
 
-                match &variant.fields {
-                    syn::Fields::Unit => {
-                        variant_tokens.extend(quote! {
-                            map(
-                                tag(#variant_name),
-                                |_| Self::#variant_ident,
-                            ),
-                        });
-                    }
-                    syn::Fields::Unnamed(fields_unnamed) => {
-                        // TODO: Require tag if there are no fields!
+    let unknown = match other {
+        Some(other) => other,
+        // Fieldless enums are plain values, like a C enum, so an unknown tag is an invalid value.
+        None if data_enum.variants.iter().all(|v| v.fields.is_empty()) => quote! {
+            ::core::result::Result::Err(#krate::__private::nom::Err::Failure(#krate::Error::at_input(
+                #krate::ErrorKind::InvalidValue { value: #tag as u64 },
+                #input,
+            )))
+        },
+        None => quote! {
+            ::core::result::Result::Err(#krate::__private::nom::Err::Error(<#krate::Error as #krate::__private::nom::error::ParseError<&[u8]>>::from_error_kind(
+                #input,
+                #krate::__private::nom::error::ErrorKind::Tag,
+            )))
+        },
+    };
+
+    quote! {
+        let #input = #buffer;
+        let (#buffer, #tag) = <#int_type as #krate::FromBytes<#de>>::from_bytes(#buffer)?;
+        match #tag {
+            #( #arms )*
+            _ => { #unknown }
+        }
+    }
+}
+
+/// Everything about the wire format of a type, parsed from its attributes.
+///
+/// Both derives parse it the same way, so they always agree on the format. They also fail the
+/// same way, with the same message at the same span, so that rustc reports each mistake once
+/// rather than once per derive.
+struct Format {
+    attrs: ContainerAttrs,
+    /// The `#[tag("...")]` of the type, encoded before anything else.
+    type_tag: Option<String>,
+    /// How the variants are tagged, if the type is an enum.
+    tag_type: TagType,
+    /// The tag of every variant, if the type is an enum.
+    variant_tags: Vec<(Tag, Span)>,
+}
+
+impl Format {
+    fn parse(input: &DeriveInput, direction: Direction) -> syn::Result<Self> {
+        if let syn::Data::Union(data_union) = &input.data {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "unions can't be encoded, since there's no telling which field is in use",
+            ));
+        }
+
+        let attrs = container_attrs(input, direction)?;
+
+        let fields: Vec<&syn::Field> = match &input.data {
+            syn::Data::Struct(data_struct) => data_struct.fields.iter().collect(),
+            syn::Data::Enum(data_enum) => data_enum
+                .variants
+                .iter()
+                .flat_map(|variant| &variant.fields)
+                .collect(),
+            syn::Data::Union(_) => unreachable!("unions are rejected above"),
+        };
+        for field in fields {
+            FieldAttrs::parse(field)?;
+        }
+
+        let type_tag = type_tag(input)?;
+
+        let (tag_type, variant_tags) = match &input.data {
+            syn::Data::Enum(data_enum) => {
+                let tag_type = enum_tag_type(&attrs, &input.attrs, data_enum)?;
+                let tags = enum_tags(data_enum, &attrs, tag_type)?;
+                check_tags(&input.ident, data_enum, &tags)?;
+                (tag_type, tags)
+            }
+            _ => (TagType::Str, Vec::new()),
+        };
 
-                        let field_bindings: Vec<_> = (0..fields_unnamed.unnamed.len())
-                            .map(|i| syn::Ident::new(&format!("f{i}"), variant_ident.span()))
-                            .collect();
+        Ok(Self {
+            attrs,
+            type_tag,
+            tag_type,
+            variant_tags,
+        })
+    }
+}
 
-                        let field_types: Vec<_> = fields_unnamed.unnamed.iter().map(|f| &f.ty).collect();
+/// How the variants of an enum are tagged, from `#[buffin(tag_type = "...")]`.
+#[derive(Clone, Copy)]
+enum TagType {
+    /// The variant name, or a `#[tag("...")]` string. This is the default.
+    Str,
+    U8,
+    U16,
+    U32,
+}
 
-                        let fields = field_types.into_iter().map(|field| {
-                            let ty_tokens = field.to_token_stream();
-                            quote! { <#ty_tokens>::from_bytes }
-                        });
+impl TagType {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        match lit.value().as_str() {
+            "u8" => Ok(TagType::U8),
+            "u16" => Ok(TagType::U16),
+            "u32" => Ok(TagType::U32),
+            _ => Err(syn::Error::new(
+                lit.span(),
+                "expected \"u8\", \"u16\" or \"u32\"",
+            )),
+        }
+    }
 
-                        variant_tokens.extend(quote! {
-                            map(
-                                (tag(#variant_name), #( #fields ),*),
-                                |(_, #( #field_bindings ),* )| Self::#variant_ident( #( #field_bindings ),* ),
-                            ),
-                        });
+    /// The integer type the tags are encoded as, unless they're strings.
+    fn int_type(self) -> Option<syn::Ident> {
+        let ty = match self {
+            TagType::Str => return None,
+            TagType::U8 => "u8",
+            TagType::U16 => "u16",
+            TagType::U32 => "u32",
+        };
+        Some(syn::Ident::new(ty, Span::call_site()))
+    }
 
+    /// The largest integer tag that fits.
+    fn max(self) -> u64 {
+        match self {
+            TagType::Str => 0,
+            TagType::U8 => u8::MAX.into(),
+            TagType::U16 => u16::MAX.into(),
+            TagType::U32 => u32::MAX.into(),
+        }
+    }
+}
+
+/// How the length of a string or collection field is encoded, from `#[buffin(len = "...")]`.
+#[derive(Clone, Copy, PartialEq)]
+enum LenPrefix {
+    U8,
+    U16,
+    U32,
+    Varint,
+}
+
+impl LenPrefix {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        match lit.value().as_str() {
+            "u8" => Ok(LenPrefix::U8),
+            "u16" => Ok(LenPrefix::U16),
+            "u32" => Ok(LenPrefix::U32),
+            "varint" => Ok(LenPrefix::Varint),
+            _ => Err(syn::Error::new(
+                lit.span(),
+                "expected \"u8\", \"u16\", \"u32\" or \"varint\"",
+            )),
+        }
+    }
+
+    /// The `buffin::len_prefix::LenPrefix` type to encode the length with.
+    fn to_tokens(self, krate: &syn::Path) -> TokenStream2 {
+        match self {
+            LenPrefix::U8 => quote! { u8 },
+            LenPrefix::U16 => quote! { u16 },
+            LenPrefix::U32 => quote! { u32 },
+            LenPrefix::Varint => quote! { #krate::len_prefix::Varint },
+        }
+    }
+}
+
+/// The tag of an enum variant.
+#[derive(PartialEq)]
+enum Tag {
+    Str(String),
+    Int(u64),
+    /// The `#[buffin(other)]` variant, which holds the tag itself.
+    Other,
+}
+
+impl Tag {
+    /// Returns the number of bytes the tag is encoded as.
+    fn len(&self, tag_type: TagType) -> usize {
+        match (self, tag_type) {
+            (Tag::Str(tag), _) => tag.len(),
+            (Tag::Int(_), TagType::U8) => 1,
+            (Tag::Int(_), TagType::U16) => 2,
+            (Tag::Int(_), TagType::U32 | TagType::Str) => 4,
+            (Tag::Other, _) => 0,
+        }
+    }
+
+    /// Builds a statement adding the tag to `buffer`.
+    fn to_bytes(&self, tag_type: TagType) -> TokenStream2 {
+        let buffer = local("buffer");
+        match (self, tag_type.int_type()) {
+            (Tag::Int(value), Some(int_type)) => {
+                let value = syn::LitInt::new(&format!("{value}{int_type}"), Span::call_site());
+                quote! { #buffer.add(&#value)?; }
+            }
+            (Tag::Str(tag), _) => quote! { #buffer.add_bytes(#tag.as_bytes())?; },
+            (Tag::Int(_), None) => unreachable!("string enums have string tags"),
+            (Tag::Other, _) => quote! {},
+        }
+    }
+
+    /// Returns the shorter and the longer of two string tags, if one starts with the other.
+    fn prefix_overlap<'a>(&'a self, other: &'a Self) -> Option<(&'a str, &'a str)> {
+        match (self, other) {
+            (Tag::Str(a), Tag::Str(b)) if a != b && b.starts_with(a.as_str()) => Some((a, b)),
+            (Tag::Str(a), Tag::Str(b)) if a != b && a.starts_with(b.as_str()) => Some((b, a)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tag::Str(tag) => write!(f, "{tag:?}"),
+            Tag::Int(tag) => write!(f, "{tag}"),
+            Tag::Other => write!(f, "any other tag"),
+        }
+    }
+}
+
+/// Returns how the variants of an enum are tagged.
+///
+/// Without a `tag_type`, fieldless enums with `#[repr(u8)]`, `#[repr(u16)]` or `#[repr(u32)]`
+/// are tagged with their discriminants, so they're encoded like a C enum. Other integer reprs
+/// are rejected, rather than quietly falling back to string tags.
+fn enum_tag_type(
+    attrs: &ContainerAttrs,
+    input_attrs: &[Attribute],
+    data_enum: &syn::DataEnum,
+) -> syn::Result<TagType> {
+    if let Some(tag_type) = attrs.tag_type {
+        return Ok(tag_type);
+    }
+
+    if data_enum.variants.iter().any(|v| !v.fields.is_empty()) {
+        return Ok(TagType::Str);
+    }
+
+    for meta in input_attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+    {
+        let Some(ident) = meta.path().get_ident() else {
+            continue;
+        };
+        match ident.to_string().as_str() {
+            "u8" => return Ok(TagType::U8),
+            "u16" => return Ok(TagType::U16),
+            "u32" => return Ok(TagType::U32),
+            "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "`#[repr({ident})]` enums can't be encoded as their discriminant, use \
+                         `u8`, `u16` or `u32`, or set a `#[buffin(tag_type = \"...\")]`"
+                    ),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(TagType::Str)
+}
+
+/// Works out the tag of every variant, along with where it came from.
+///
+/// String tags default to the variant name. Integer tags are taken from `#[tag(3)]` or the
+/// discriminant, and otherwise count up from the previous variant, like discriminants do. The
+/// `#[buffin(other)]` variant has no tag of its own.
+fn enum_tags(
+    data_enum: &syn::DataEnum,
+    attrs: &ContainerAttrs,
+    tag_type: TagType,
+) -> syn::Result<Vec<(Tag, Span)>> {
+    let mut tags = Vec::new();
+    let mut next = 0;
+
+    for variant in &data_enum.variants {
+        let explicit = tag_attr(&variant.attrs)?;
+
+        if is_other(variant)? {
+            let message = if !attrs.length_delimited {
+                "`#[buffin(other)]` needs `#[buffin(length_delimited)]` on the enum, so that \
+                 unknown variants can be skipped"
+            } else if tags.iter().any(|(tag, _)| *tag == Tag::Other) {
+                "only one variant can be `#[buffin(other)]`"
+            } else if explicit.is_some() {
+                "`#[buffin(other)]` catches every unknown tag, so it can't have a `#[tag(...)]`"
+            } else if variant.fields.len() != 2
+                || variant
+                    .fields
+                    .iter()
+                    .flat_map(|field| &field.attrs)
+                    .any(|attr| attr.path().is_ident("buffin"))
+            {
+                "`#[buffin(other)]` variants hold the tag and the payload, e.g. \
+                 `Unknown(u8, Vec<u8>)`"
+            } else {
+                check_other_fields(variant, tag_type)?;
+                tags.push((Tag::Other, variant.ident.span()));
+                continue;
+            };
+            return Err(syn::Error::new(variant.ident.span(), message));
+        }
+
+        let (tag, span) = match (tag_type, explicit) {
+            (TagType::Str, None) => (Tag::Str(variant.ident.to_string()), variant.ident.span()),
+            (TagType::Str, Some(Lit::Str(lit))) => (Tag::Str(lit.value()), lit.span()),
+            (TagType::Str, Some(lit)) => {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    "expected a string tag, or `#[buffin(tag_type = \"u8\")]` on the enum \
+                     for integer tags",
+                ));
+            }
+            (_, Some(Lit::Int(lit))) => (Tag::Int(lit.base10_parse()?), lit.span()),
+            (_, Some(lit)) => {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    "expected an integer tag, since the enum has a `tag_type`",
+                ));
+            }
+            (_, None) => match &variant.discriminant {
+                Some((
+                    _,
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(lit), ..
+                    }),
+                )) => (Tag::Int(lit.base10_parse()?), lit.span()),
+                Some((_, expr)) => {
+                    return Err(syn::Error::new(
+                        expr.span(),
+                        "integer tags can only be taken from literal discriminants, \
+                         use `#[tag(...)]` instead",
+                    ));
+                }
+                None => (Tag::Int(next), variant.ident.span()),
+            },
+        };
+
+        if let Tag::Int(value) = tag {
+            if value > tag_type.max() {
+                return Err(syn::Error::new(
+                    span,
+                    format!("tag {value} doesn't fit in the `tag_type`"),
+                ));
+            }
+            next = value + 1;
+        }
+
+        tags.push((tag, span));
+    }
+
+    Ok(tags)
+}
+
+/// Checks that the fields of the `#[buffin(other)]` variant can hold the tag and the payload,
+/// which is the tag type followed by `Vec<u8>` or `&[u8]`.
+fn check_other_fields(variant: &syn::Variant, tag_type: TagType) -> syn::Result<()> {
+    let mut fields = variant.fields.iter().map(|field| &field.ty);
+    let (Some(tag), Some(payload)) = (fields.next(), fields.next()) else {
+        unreachable!("the variant has two fields");
+    };
+    let int_type = tag_type
+        .int_type()
+        .expect("length delimited enums have a tag_type");
+
+    if !is_path_to(tag, &int_type.to_string()) {
+        return Err(syn::Error::new(
+            tag.span(),
+            format!("the tag of a `#[buffin(other)]` variant must be the `tag_type`, `{int_type}`"),
+        ));
+    }
+
+    let is_bytes = match payload {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            path.path.segments.last().is_some_and(|segment| {
+                segment.ident == "Vec"
+                    && matches!(
+                        &segment.arguments,
+                        syn::PathArguments::AngleBracketed(args)
+                            if args.args.len() == 1
+                                && matches!(
+                                    &args.args[0],
+                                    syn::GenericArgument::Type(ty) if is_path_to(ty, "u8")
+                                )
+                    )
+            })
+        }
+        syn::Type::Reference(reference) if reference.mutability.is_none() => matches!(
+            &*reference.elem,
+            syn::Type::Slice(slice) if is_path_to(&slice.elem, "u8")
+        ),
+        _ => false,
+    };
+    if !is_bytes {
+        return Err(syn::Error::new(
+            payload.span(),
+            "the payload of a `#[buffin(other)]` variant must be `Vec<u8>` or `&[u8]`",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns whether a type is a bare path to `name`, like `u8`.
+fn is_path_to(ty: &syn::Type, name: &str) -> bool {
+    match ty {
+        syn::Type::Path(path) => path.qself.is_none() && path.path.is_ident(name),
+        _ => false,
+    }
+}
+
+/// Returns whether a variant is marked `#[buffin(other)]`, which is the only `buffin` attribute
+/// variants take.
+fn is_other(variant: &syn::Variant) -> syn::Result<bool> {
+    let mut other = false;
+
+    for attr in &variant.attrs {
+        if !attr.path().is_ident("buffin") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("other") {
+                other = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported buffin attribute"))
+            }
+        })?;
+    }
+
+    Ok(other)
+}
+
+/// Returns the string in the `#[tag("...")]` of the type, which is encoded before anything else.
+///
+/// Structs without fields to encode must have one, since they'd otherwise be encoded as nothing
+/// at all.
+fn type_tag(input: &DeriveInput) -> syn::Result<Option<String>> {
+    let tag = match tag_attr(&input.attrs)? {
+        None => None,
+        Some(Lit::Str(lit)) => Some(lit.value()),
+        Some(lit) => return Err(syn::Error::new(lit.span(), "expected a string tag")),
+    };
+
+    match &input.data {
+        syn::Data::Struct(data_struct)
+            if tag.is_none()
+                && data_struct
+                    .fields
+                    .iter()
+                    .all(|field| FieldAttrs::of(field).skip) =>
+        {
+            Err(syn::Error::new(
+                input.ident.span(),
+                "structs without fields to encode must have a `#[tag(\"...\")]`",
+            ))
+        }
+        _ => Ok(tag),
+    }
+}
+
+/// Returns the literal in `#[tag(...)]` or `#[tag = ...]`, if there is one.
+fn tag_attr(attrs: &[Attribute]) -> syn::Result<Option<Lit>> {
+    let mut tag = None;
+
+    for attr in attrs {
+        if !attr.path().is_ident("tag") {
+            continue;
+        }
+
+        let expr = match &attr.meta {
+            Meta::List(list) => list.parse_args::<Expr>()?,
+            Meta::NameValue(nv) => nv.value.clone(),
+            Meta::Path(path) => return Err(syn::Error::new(path.span(), "expected `#[tag(...)]`")),
+        };
+
+        match expr {
+            Expr::Lit(ExprLit { lit, .. }) => tag = Some(lit),
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "expected a string or integer literal",
+                ));
+            }
+        }
+    }
+
+    Ok(tag)
+}
+
+/// Rejects tags that are the same as, or a prefix of, another variant's tag.
+///
+/// Variants are tried in order, so such a variant would either never be decoded, or be decoded
+/// from the start of another variant's tag.
+fn check_tags(
+    name: &syn::Ident,
+    data_enum: &syn::DataEnum,
+    tags: &[(Tag, Span)],
+) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    let variants: Vec<_> = data_enum.variants.iter().zip(tags).collect();
+
+    for (i, (variant, (tag, span))) in variants.iter().enumerate() {
+        for (other_variant, (other_tag, _)) in &variants[..i] {
+            let (variant, other_variant) = (&variant.ident, &other_variant.ident);
+            let message = if tag == other_tag {
+                format!(
+                    "`{name}::{variant}` has the same tag as `{name}::{other_variant}`: {tag}, \
+                     give one of them a different `#[tag(...)]`"
+                )
+            } else if let Some((prefix, longer)) = tag.prefix_overlap(other_tag) {
+                format!(
+                    "the tags of `{name}::{variant}` and `{name}::{other_variant}` are ambiguous, \
+                     since {prefix:?} is a prefix of {longer:?}, give one of them a \
+                     `#[tag(\"...\")]` that doesn't start with the other"
+                )
+            } else {
+                continue;
+            };
+
+            let error = syn::Error::new(*span, message);
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        }
+    }
+
+    errors.map_or(Ok(()), Err)
+}
+
+/// Builds a parser for a field of an enum variant.
+///
+/// Once the tag of a variant has matched, its fields are parsed with `cut`, so that their errors
+/// are reported rather than moving on to the next variant. `decode` decodes the field from
+/// `buffer`.
+fn variant_field_parser(krate: &syn::Path, decode: TokenStream2, field_path: &str) -> TokenStream2 {
+    let buffer = local("buffer");
+    let nom = quote! { #krate::__private::nom };
+    quote! {
+        move |#buffer| #nom::Parser::parse(&mut #nom::combinator::cut(move |#buffer| #decode), #buffer)
+            .map_err(|err| err.map(|err: #krate::Error| err.in_field(#field_path)))
+    }
+}
+
+/// A field of a struct or enum variant.
+struct FieldInfo<'a> {
+    field: &'a syn::Field,
+    /// What the generated code binds the field to, `f0` and so on for tuple fields.
+    binding: syn::Ident,
+    /// Where the field is, for errors, e.g. `"Message::Say.channel"`.
+    path: String,
+    attrs: FieldAttrs,
+}
+
+impl FieldInfo<'_> {
+    /// Builds an expression referring to the field as something that implements `ToBytes`.
+    fn encodable(&self, krate: &syn::Path) -> TokenStream2 {
+        let binding = &self.binding;
+        if let Some(len) = self.attrs.len {
+            let prefix = len.to_tokens(krate);
+            return quote! { &#krate::len_prefix::Prefixed::<#prefix, _>::new(#binding) };
+        }
+
+        match self.attrs.serializer() {
+            Some((to_bytes, encoded_len)) => quote! {
+                &#krate::__private::With::new(#binding, #to_bytes, #encoded_len)
+            },
+            None => quote! { #binding },
+        }
+    }
+
+    /// Builds an expression decoding the field from `input`. Expects `limits` to be in scope.
+    fn decode(&self, krate: &syn::Path, de: &syn::Lifetime, input: TokenStream2) -> TokenStream2 {
+        let limits = local("limits");
+        if let Some(len) = self.attrs.len {
+            let (prefix, ty) = (len.to_tokens(krate), &self.field.ty);
+            return quote! {
+                <#krate::len_prefix::Prefixed<#prefix, #ty> as #krate::FromBytes<#de>>::from_bytes_limited(#input, #limits)
+                    .map(|(buffer, value)| (buffer, value.into_inner()))
+            };
+        }
+
+        match self.attrs.deserializer() {
+            Some(from_bytes) => quote! { #from_bytes(#input) },
+            None => {
+                let ty = &self.field.ty;
+                quote! { <#ty as #krate::FromBytes<#de>>::from_bytes_limited(#input, #limits) }
+            }
+        }
+    }
+}
+
+/// Builds the name of a local in the generated code. The mixed-site span keeps it apart from the
+/// bindings of the fields, so that e.g. a field named `buffer` doesn't shadow it.
+fn local(name: &str) -> syn::Ident {
+    syn::Ident::new(name, Span::mixed_site())
+}
+
+/// Returns the fields of a struct or variant, where `prefix` is its path, e.g. `"Message::Say"`.
+fn fields_of<'a>(fields: &'a syn::Fields, prefix: &str) -> Vec<FieldInfo<'a>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let (binding, path) = match &field.ident {
+                Some(ident) => (ident.clone(), format!("{prefix}.{ident}")),
+                None => (
+                    syn::Ident::new(&format!("f{i}"), Span::call_site()),
+                    format!("{prefix}.{i}"),
+                ),
+            };
+            FieldInfo {
+                field,
+                binding,
+                path,
+                attrs: FieldAttrs::of(field),
+            }
+        })
+        .collect()
+}
+
+/// Builds a pattern for `path` that binds the fields that are encoded, and ignores the rest.
+fn destructure(path: TokenStream2, fields: &syn::Fields, infos: &[FieldInfo]) -> TokenStream2 {
+    let parts = infos.iter().map(|info| {
+        let binding = &info.binding;
+        match (&info.field.ident, info.attrs.skip) {
+            (_, false) => quote! { #binding },
+            (Some(_), true) => quote! { #binding: _ },
+            (None, true) => quote! { _ },
+        }
+    });
+
+    match fields {
+        syn::Fields::Named(_) => quote! { #path { #( #parts ),* } },
+        syn::Fields::Unnamed(_) => quote! { #path( #( #parts ),* ) },
+        syn::Fields::Unit => path,
+    }
+}
+
+/// Builds an expression for `path`, made from the bindings of its fields.
+fn construct(path: TokenStream2, fields: &syn::Fields, infos: &[FieldInfo]) -> TokenStream2 {
+    let bindings = infos.iter().map(|info| &info.binding);
+
+    match fields {
+        syn::Fields::Named(_) => quote! { #path { #( #bindings ),* } },
+        syn::Fields::Unnamed(_) => quote! { #path( #( #bindings ),* ) },
+        syn::Fields::Unit => path,
+    }
+}
+
+/// Builds statements decoding the fields one after the other, from `buffer`, or from `payload`
+/// if the variant is length-delimited. Skipped fields get their default instead.
+///
+/// Expects `limits` to be in scope, and `rest` too for payloads.
+fn decode_fields<'a>(
+    krate: &syn::Path,
+    de: &syn::Lifetime,
+    fields: impl IntoIterator<Item = &'a FieldInfo<'a>>,
+    from_payload: bool,
+) -> Vec<TokenStream2> {
+    let buffer = local("buffer");
+    let (payload, rest) = (local("payload"), local("rest"));
+    fields
+        .into_iter()
+        .map(|info| {
+            let (binding, path) = (&info.binding, &info.path);
+
+            if info.attrs.skip {
+                let default = info.attrs.default_value();
+                quote! { let #binding = #default; }
+            } else if from_payload {
+                let decode = info.decode(krate, de, quote! { #payload });
+                quote! {
+                    let (#payload, #binding) = #krate::__private::in_payload(#decode, #rest)
+                        .map_err(|err| err.map(|err| err.in_field(#path)))?;
+                }
+            } else {
+                let decode = info.decode(krate, de, quote! { #buffer });
+                quote! {
+                    let (#buffer, #binding) = #decode
+                        .map_err(|err| err.map(|err| err.in_field(#path)))?;
+                }
+            }
+        })
+        .collect()
+}
+
+/// Builds an expression for the `MAX_SIZE` of a tag followed by the given fields.
+fn max_size_of<'a>(
+    krate: &syn::Path,
+    tag_len: usize,
+    fields: impl IntoIterator<Item = &'a FieldInfo<'a>>,
+) -> TokenStream2 {
+    fields
+        .into_iter()
+        .fold(quote! { ::core::option::Option::Some(#tag_len) }, |acc, info| {
+            let ty = &info.field.ty;
+            // There's no telling how large a field with custom functions gets.
+            let max_size = match (info.attrs.len, info.attrs.serializer()) {
+                (Some(len), _) => {
+                    let prefix = len.to_tokens(krate);
+                    quote! { <#krate::len_prefix::Prefixed<#prefix, #ty> as #krate::ToBytes>::MAX_SIZE }
+                }
+                (None, Some(_)) => quote! { ::core::option::Option::None },
+                (None, None) => quote! { <#ty as #krate::ToBytes>::MAX_SIZE },
+            };
+            quote! { #krate::max_size::sum(#acc, #max_size) }
+        })
+}
+
+#[derive(Clone, Copy)]
+enum Direction<'a> {
+    ToBytes,
+    /// With the lifetime decoded from.
+    FromBytes(&'a syn::Lifetime),
+}
+
+impl Direction<'_> {
+    /// The key used for this direction in `#[buffin(bound(...))]`.
+    fn key(self) -> &'static str {
+        match self {
+            Direction::ToBytes => "to_bytes",
+            Direction::FromBytes(_) => "from_bytes",
+        }
+    }
+
+    fn trait_path(self, krate: &syn::Path) -> TokenStream2 {
+        match self {
+            Direction::ToBytes => quote! { #krate::ToBytes },
+            Direction::FromBytes(de) => quote! { #krate::FromBytes<#de> },
+        }
+    }
+}
+
+/// Returns the generics of the input with a where-clause suitable for the generated impl.
+///
+/// Every type parameter gets a `ToBytes`/`FromBytes` bound, unless the bounds have been
+/// overridden with `#[buffin(bound = "...")]` or `#[buffin(bound(to_bytes = "...", from_bytes = "..."))]`.
+fn with_bounds(generics: &Generics, attrs: &ContainerAttrs, direction: Direction) -> Generics {
+    let mut generics = generics.clone();
+
+    let predicates = match &attrs.bounds {
+        Some(predicates) => predicates.clone(),
+        None => {
+            let trait_path = direction.trait_path(&attrs.krate());
+            generics
+                .type_params()
+                .map(|param| {
+                    let ident = &param.ident;
+                    parse_quote! { #ident: #trait_path }
+                })
+                .collect()
+        }
+    };
+
+    generics.make_where_clause().predicates.extend(predicates);
+    generics
+}
+
+/// Returns the lifetime `FromBytes` decodes from, `'de` unless the type has a lifetime by that
+/// name already.
+fn de_lifetime(generics: &Generics) -> syn::Lifetime {
+    let taken = |name: &str| {
+        generics
+            .lifetimes()
+            .any(|param| param.lifetime.ident == name)
+    };
+    let name = iter::once("de".to_string())
+        .chain((1..).map(|i| format!("de{i}")))
+        .find(|name| !taken(name))
+        .expect("there are more names than lifetimes");
+    syn::Lifetime::new(&format!("'{name}"), Span::call_site())
+}
+
+/// Adds the lifetime `de` that `FromBytes` decodes from, outliving every lifetime of the type.
+fn with_de_lifetime(mut generics: Generics, de: &syn::Lifetime) -> Generics {
+    let mut de = LifetimeParam::new(de.clone());
+    de.bounds
+        .extend(generics.lifetimes().map(|param| param.lifetime.clone()));
+    de.colon_token = (!de.bounds.is_empty()).then(Default::default);
+    generics.params.insert(0, GenericParam::Lifetime(de));
+    generics
+}
+
+/// The `#[buffin(...)]` attributes of the type itself.
+#[derive(Default)]
+struct ContainerAttrs {
+    /// From `bound`, for the direction being derived.
+    bounds: Option<Vec<WherePredicate>>,
+    /// From `tag_type`, only allowed on enums.
+    tag_type: Option<TagType>,
+    /// From `length_delimited`, only allowed on enums with a `tag_type`.
+    length_delimited: bool,
+    /// From `crate`, for when `buffin` is only available through a re-export.
+    krate: Option<syn::Path>,
+}
+
+impl ContainerAttrs {
+    /// The path the generated code refers to `buffin` by.
+    fn krate(&self) -> syn::Path {
+        self.krate
+            .clone()
+            .unwrap_or_else(|| parse_quote! { ::buffin })
+    }
+}
+
+/// The `#[buffin(...)]` attributes of a field.
+#[derive(Default)]
+struct FieldAttrs {
+    /// From `skip`, the field isn't encoded, and gets its default value when decoding.
+    skip: bool,
+    /// From `default`, the function giving a skipped field its value.
+    default: Option<syn::ExprPath>,
+    /// From `with`, a module with `to_bytes`, `encoded_len` and `from_bytes` functions.
+    with: Option<syn::Path>,
+    /// From `serialize_with` and `encoded_len_with`, which go together.
+    serialize_with: Option<syn::ExprPath>,
+    encoded_len_with: Option<syn::ExprPath>,
+    /// From `deserialize_with`.
+    deserialize_with: Option<syn::ExprPath>,
+    /// From `len`, how the length of a string or collection is encoded.
+    len: Option<LenPrefix>,
+}
+
+impl FieldAttrs {
+    /// Parses `#[buffin(skip)]`, `#[buffin(default = "...")]`, `#[buffin(with = "...")]`,
+    /// `#[buffin(serialize_with = "...", encoded_len_with = "...")]`,
+    /// `#[buffin(deserialize_with = "...")]` and `#[buffin(len = "...")]`.
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attrs = FieldAttrs::default();
+        let mut spans = Vec::new();
+
+        for attr in &field.attrs {
+            if !attr.path().is_ident("buffin") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                    return Ok(());
+                }
+
+                let key = meta
+                    .path
+                    .get_ident()
+                    .map(ToString::to_string)
+                    .unwrap_or_default();
+                let slot = match key.as_str() {
+                    "default" => &mut attrs.default,
+                    "serialize_with" => &mut attrs.serialize_with,
+                    "encoded_len_with" => &mut attrs.encoded_len_with,
+                    "deserialize_with" => &mut attrs.deserialize_with,
+                    "with" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        attrs.with = Some(lit.parse()?);
+                        spans.push((key, lit.span()));
+                        return Ok(());
                     }
-                    syn::Fields::Named(fields_named) => {
-                        let field_idents: Vec<_> = fields_named
-                            .named
-                            .iter()
-                            .map(|f| f.ident.as_ref().expect(&format!("{}:{}", file!(), line!())))
-                            .collect();
-
-                        let field_types: Vec<_> = fields_named.named.iter().map(|f| &f.ty).collect();
-
-                        let fields = field_types.into_iter().map(|field| {
-                            let ty_tokens = field.to_token_stream();
-                            quote! { <#ty_tokens>::from_bytes }
-                        });
-
-                        variant_tokens.extend(quote! {
-                            map(
-                                (tag(#variant_name), #( #fields ),*),
-                                |(_, #( #field_idents ),* )| Self::#variant_ident{ #( #field_idents ),* },
-                            ),
-                        });
+                    "len" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        attrs.len = Some(LenPrefix::parse(&lit)?);
+                        spans.push((key, lit.span()));
+                        return Ok(());
                     }
+                    _ => return Err(meta.error("unsupported buffin attribute")),
+                };
+                let lit: LitStr = meta.value()?.parse()?;
+                *slot = Some(lit.parse()?);
+                spans.push((key, lit.span()));
+                Ok(())
+            })?;
+        }
+
+        let span_of = |key: &str| spans.iter().find(|(k, _)| k == key).map(|(_, span)| *span);
+        let custom = attrs.with.is_some()
+            || attrs.serialize_with.is_some()
+            || attrs.encoded_len_with.is_some()
+            || attrs.deserialize_with.is_some();
+
+        let error = if let (Some(span), false) = (span_of("default"), attrs.skip) {
+            Some((
+                span,
+                "`default` only applies to fields with `#[buffin(skip)]`",
+            ))
+        } else if let (Some(span), true) = (span_of("len"), attrs.skip) {
+            Some((
+                span,
+                "skipped fields aren't encoded, so they can't have a length prefix",
+            ))
+        } else if let (Some(span), true) = (span_of("len"), custom) {
+            Some((
+                span,
+                "`len` can't be combined with custom functions, which encode the length themselves",
+            ))
+        } else if attrs.skip && custom {
+            let span = spans
+                .iter()
+                .find(|(k, _)| k != "default")
+                .map(|(_, span)| *span);
+            Some((
+                span.expect("custom functions have a span"),
+                "skipped fields aren't encoded, so they can't have custom functions",
+            ))
+        } else if let (Some(span), true) = (
+            span_of("with"),
+            attrs.serialize_with.is_some()
+                || attrs.encoded_len_with.is_some()
+                || attrs.deserialize_with.is_some(),
+        ) {
+            Some((
+                span,
+                "`with` already gives all the functions, so it can't be combined with \
+                 `serialize_with`, `encoded_len_with` or `deserialize_with`",
+            ))
+        } else if let (Some(span), None) = (span_of("serialize_with"), &attrs.encoded_len_with) {
+            Some((
+                span,
+                "`serialize_with` needs `encoded_len_with` too, so the encoded length is known \
+                 before encoding",
+            ))
+        } else if let (Some(span), None) = (span_of("encoded_len_with"), &attrs.serialize_with) {
+            Some((
+                span,
+                "`encoded_len_with` only applies along with `serialize_with`",
+            ))
+        } else {
+            None
+        };
+
+        match error {
+            Some((span, message)) => Err(syn::Error::new(span, message)),
+            None => Ok(attrs),
+        }
+    }
+
+    /// Returns the attributes of a field, which [`Format::parse`] has already checked.
+    fn of(field: &syn::Field) -> Self {
+        Self::parse(field).expect("field attributes are checked up front")
+    }
+
+    /// Returns the functions encoding the field and giving its encoded length, if it has custom
+    /// ones.
+    fn serializer(&self) -> Option<(TokenStream2, TokenStream2)> {
+        if let Some(module) = &self.with {
+            return Some((
+                quote! { #module::to_bytes },
+                quote! { #module::encoded_len },
+            ));
+        }
+
+        match (&self.serialize_with, &self.encoded_len_with) {
+            (Some(to_bytes), Some(encoded_len)) => {
+                Some((quote! { #to_bytes }, quote! { #encoded_len }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the function decoding the field, if it has a custom one.
+    fn deserializer(&self) -> Option<TokenStream2> {
+        match (&self.with, &self.deserialize_with) {
+            (Some(module), _) => Some(quote! { #module::from_bytes }),
+            (None, Some(from_bytes)) => Some(quote! { #from_bytes }),
+            (None, None) => None,
+        }
+    }
+
+    /// Builds an expression for the value of a skipped field.
+    fn default_value(&self) -> TokenStream2 {
+        match &self.default {
+            Some(path) => quote! { #path() },
+            None => quote! { ::core::default::Default::default() },
+        }
+    }
+}
+
+/// Parses `#[buffin(bound = "...")]`, `#[buffin(bound(to_bytes = "...", from_bytes = "..."))]`,
+/// `#[buffin(tag_type = "...")]`, `#[buffin(length_delimited)]` and `#[buffin(crate = "...")]`.
+fn container_attrs(input: &DeriveInput, direction: Direction) -> syn::Result<ContainerAttrs> {
+    let mut attrs = ContainerAttrs::default();
+    let mut bounds = None;
+    let mut length_delimited = None;
+
+    for attr in &input.attrs {
+        if !attr.path().is_ident("buffin") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                if meta.input.peek(Token![=]) {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    bounds = Some(parse_predicates(&lit)?);
+                    return Ok(());
                 }
-            });
 
-            let expanded = quote! {
-                impl buffin::FromBytes for #name {
-                    fn from_bytes(buffer: &[u8]) -> nom::IResult<&[u8], Self> {
-                        use nom::{Parser, branch::alt, combinator::map, bytes::complete::tag};
-                        #get_type_tag
-                        alt(( #variant_tokens )).parse(buffer)
+                return meta.parse_nested_meta(|inner| {
+                    if !inner.path.is_ident("to_bytes") && !inner.path.is_ident("from_bytes") {
+                        return Err(inner.error("expected `to_bytes` or `from_bytes`"));
+                    }
+                    // Both directions are parsed, so that both derives report the same errors.
+                    let lit: LitStr = inner.value()?.parse()?;
+                    let predicates = parse_predicates(&lit)?;
+                    if inner.path.is_ident(direction.key()) {
+                        bounds = Some(predicates);
                     }
+                    Ok(())
+                });
+            }
+
+            if meta.path.is_ident("tag_type") {
+                let lit: LitStr = meta.value()?.parse()?;
+                if !matches!(input.data, syn::Data::Enum(_)) {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        "`tag_type` can only be used on enums",
+                    ));
                 }
-            };
+                attrs.tag_type = Some(TagType::parse(&lit)?);
+                return Ok(());
+            }
 
-            expanded.into()
-        }
-        other => {
-            let other = format!("{other:?}");
-            quote! {
-                compile_error!("`#[derive(FromBytes)]` cannot be used for {:?}", #other);
+            if meta.path.is_ident("crate") {
+                let lit: LitStr = meta.value()?.parse()?;
+                attrs.krate = Some(lit.parse()?);
+                return Ok(());
             }
-            .into()
+
+            if meta.path.is_ident("length_delimited") {
+                if !matches!(input.data, syn::Data::Enum(_)) {
+                    return Err(meta.error("`length_delimited` can only be used on enums"));
+                }
+                length_delimited = Some(meta.path.span());
+                return Ok(());
+            }
+
+            Err(meta.error("unsupported buffin attribute"))
+        })?;
+    }
+
+    // There's no telling where an unknown string tag ends, so skipping unknown variants needs
+    // integer tags.
+    if let Some(span) = length_delimited {
+        if attrs.tag_type.is_none() {
+            return Err(syn::Error::new(
+                span,
+                "`length_delimited` needs integer tags, add `#[buffin(tag_type = \"u8\")]`",
+            ));
         }
+        attrs.length_delimited = true;
     }
+
+    attrs.bounds = bounds;
+    Ok(attrs)
+}
+
+fn parse_predicates(lit: &LitStr) -> syn::Result<Vec<WherePredicate>> {
+    let predicates = lit.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+    Ok(predicates.into_iter().collect())
 }
@@ -0,0 +1,7 @@
+//! Misusing the derives is reported as a compile error, pointing at the mistake.
+
+#[test]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,14 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+fn zero() -> u32 {
+    0
+}
+
+#[derive(ToBytes, FromBytes)]
+struct Point {
+    x: u8,
+    #[buffin(default = "zero")]
+    hash: u32,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(tag_type = "u8")]
+enum Message {
+    #[tag(1)]
+    Ping,
+    Pong,
+    #[tag(1)]
+    Quit,
+}
+
+fn main() {}
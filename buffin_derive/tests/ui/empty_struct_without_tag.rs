@@ -0,0 +1,6 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+struct Empty {}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+enum Message {
+    #[tag(1)]
+    Ping,
+}
+
+fn main() {}
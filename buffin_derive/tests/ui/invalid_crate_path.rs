@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(crate = "not a path")]
+struct Point {
+    x: u8,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(bound(to_bytes = "T ToBytes", from_bytes = "T: FromBytes<'de>"))]
+struct Packet<T> {
+    body: T,
+}
+
+#[derive(FromBytes)]
+#[buffin(bound(to_bytes = "T ToBytes"))]
+struct Incoming<T> {
+    body: T,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+struct Name {
+    #[buffin(skip, len = "u8")]
+    value: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+struct Name {
+    #[buffin(len = "u8", with = "codec")]
+    value: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(length_delimited)]
+struct Point {
+    x: u8,
+}
+
+fn main() {}
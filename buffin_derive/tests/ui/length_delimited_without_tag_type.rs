@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(length_delimited)]
+enum Message {
+    Ping,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+const PING: u8 = 1;
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(tag_type = "u8")]
+#[repr(u8)]
+enum Message {
+    Ping = PING,
+}
+
+fn main() {}
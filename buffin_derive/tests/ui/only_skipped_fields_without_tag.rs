@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+struct Cache {
+    #[buffin(skip)]
+    hits: u32,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum Message {
+    Ping,
+    #[buffin(other)]
+    Unknown(u8, #[buffin(skip)] Vec<u8>),
+}
+
+fn main() {}
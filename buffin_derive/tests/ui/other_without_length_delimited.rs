@@ -0,0 +1,11 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(tag_type = "u8")]
+enum Message {
+    Ping,
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+}
+
+fn main() {}
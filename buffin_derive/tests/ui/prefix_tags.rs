@@ -0,0 +1,10 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+enum Message {
+    #[tag("S")]
+    Stop,
+    Say(String),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(tag_type = "u8")]
+enum Message {
+    #[tag("p")]
+    Ping,
+}
+
+fn main() {}
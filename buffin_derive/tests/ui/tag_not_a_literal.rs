@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+const TAG: &str = "ack";
+
+#[derive(ToBytes, FromBytes)]
+#[tag(TAG)]
+struct Ack;
+
+fn main() {}
@@ -0,0 +1,11 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(tag_type = "u8")]
+enum Message {
+    Ping,
+    #[tag(256)]
+    Pong,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[tag]
+struct Ack;
+
+fn main() {}
@@ -0,0 +1,12 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(tag_type = "u8", length_delimited)]
+enum Message {
+    #[buffin(other)]
+    Unknown(u8, Vec<u8>),
+    #[buffin(other)]
+    Unexpected(u8, Vec<u8>),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
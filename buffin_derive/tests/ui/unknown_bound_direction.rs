@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(bound(encode = "T: ToBytes"))]
+struct Packet<T> {
+    body: T,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[buffin(compact)]
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+struct Point {
+    #[buffin(rename = "y")]
+    x: u8,
+}
+
+fn main() {}
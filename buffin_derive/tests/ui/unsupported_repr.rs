@@ -0,0 +1,10 @@
+use buffin_derive::{FromBytes, ToBytes};
+
+#[derive(ToBytes, FromBytes)]
+#[repr(i16)]
+enum Level {
+    Low = 0,
+    High = 1,
+}
+
+fn main() {}